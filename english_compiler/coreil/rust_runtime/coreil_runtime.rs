@@ -11,11 +11,11 @@
 // Note: #![allow(...)] attributes are emitted in the generated program.rs
 // (the crate root) since include!() files cannot use inner attributes.
 
-use std::cell::RefCell;
 use std::cmp::Ordering;
 use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
 use std::fmt;
-use std::rc::Rc;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
 
 // ============================================================================
 // OrderedMap - Python dict semantics (insertion-order preserving)
@@ -24,7 +24,8 @@ use std::rc::Rc;
 #[derive(Clone, Debug)]
 struct OrderedMap {
     entries: Vec<(Value, Value)>,
-    index: HashMap<String, usize>,
+    // Structural hash -> candidate entry indices (hash collisions share a bucket).
+    index: HashMap<u64, Vec<usize>>,
 }
 
 impl OrderedMap {
@@ -35,20 +36,30 @@ impl OrderedMap {
         }
     }
 
+    fn find_index(&self, key: &Value) -> Option<usize> {
+        let h = hash_value(key);
+        self.index
+            .get(&h)?
+            .iter()
+            .copied()
+            .find(|&idx| values_equal(&self.entries[idx].0, key))
+    }
+
     fn set(&mut self, key: Value, value: Value) {
-        let skey = serialize_value(&key);
-        if let Some(&idx) = self.index.get(&skey) {
+        check_hashable(&key);
+        if let Some(idx) = self.find_index(&key) {
             self.entries[idx].1 = value;
         } else {
             let idx = self.entries.len();
+            let h = hash_value(&key);
             self.entries.push((key, value));
-            self.index.insert(skey, idx);
+            self.index.entry(h).or_insert_with(Vec::new).push(idx);
         }
     }
 
     fn get(&self, key: &Value) -> Option<&Value> {
-        let skey = serialize_value(key);
-        self.index.get(&skey).map(|&idx| &self.entries[idx].1)
+        check_hashable(key);
+        self.find_index(key).map(|idx| &self.entries[idx].1)
     }
 
     fn get_default(&self, key: &Value, default: &Value) -> Value {
@@ -67,8 +78,23 @@ impl OrderedMap {
     }
 
     fn contains_key(&self, key: &Value) -> bool {
-        let skey = serialize_value(key);
-        self.index.contains_key(&skey)
+        check_hashable(key);
+        self.find_index(key).is_some()
+    }
+
+    fn rebuild_index(&mut self) {
+        self.index.clear();
+        for (i, (k, _)) in self.entries.iter().enumerate() {
+            self.index.entry(hash_value(k)).or_insert_with(Vec::new).push(i);
+        }
+    }
+
+    fn remove(&mut self, key: &Value) {
+        check_hashable(key);
+        if let Some(idx) = self.find_index(key) {
+            self.entries.remove(idx);
+            self.rebuild_index();
+        }
     }
 }
 
@@ -79,35 +105,54 @@ impl OrderedMap {
 #[derive(Clone, Debug)]
 struct OrderedSet {
     items: Vec<Value>,
-    index: HashSet<String>,
+    // Structural hash -> candidate item indices (hash collisions share a bucket).
+    index: HashMap<u64, Vec<usize>>,
 }
 
 impl OrderedSet {
     fn new() -> Self {
         OrderedSet {
             items: Vec::new(),
-            index: HashSet::new(),
+            index: HashMap::new(),
+        }
+    }
+
+    fn find_index(&self, item: &Value) -> Option<usize> {
+        let h = hash_value(item);
+        self.index
+            .get(&h)?
+            .iter()
+            .copied()
+            .find(|&idx| values_equal(&self.items[idx], item))
+    }
+
+    fn rebuild_index(&mut self) {
+        self.index.clear();
+        for (i, v) in self.items.iter().enumerate() {
+            self.index.entry(hash_value(v)).or_insert_with(Vec::new).push(i);
         }
     }
 
     fn add(&mut self, item: Value) {
-        let skey = serialize_value(&item);
-        if !self.index.contains(&skey) {
-            self.index.insert(skey);
+        check_hashable(&item);
+        if self.find_index(&item).is_none() {
+            let h = hash_value(&item);
+            let idx = self.items.len();
             self.items.push(item);
+            self.index.entry(h).or_insert_with(Vec::new).push(idx);
         }
     }
 
     fn has(&self, item: &Value) -> bool {
-        let skey = serialize_value(item);
-        self.index.contains(&skey)
+        check_hashable(item);
+        self.find_index(item).is_some()
     }
 
     fn remove(&mut self, item: &Value) {
-        let skey = serialize_value(item);
-        if self.index.remove(&skey) {
-            self.items.retain(|v| serialize_value(v) != skey);
-            // Rebuild index positions are not needed since we use HashSet
+        check_hashable(item);
+        if let Some(idx) = self.find_index(item) {
+            self.items.remove(idx);
+            self.rebuild_index();
         }
     }
 
@@ -198,6 +243,278 @@ impl CoreILHeap {
     }
 }
 
+// ============================================================================
+// BigInt - arbitrary-precision integer (sign + base-2^32 magnitude limbs)
+// ============================================================================
+
+/// Little-endian base-2^32 limbs, no leading-zero limbs; canonical zero is
+/// an empty magnitude with `positive = true`.
+#[derive(Clone, Debug)]
+struct BigIntVal {
+    positive: bool,
+    mag: Vec<u32>,
+}
+
+fn bigint_normalize(mut mag: Vec<u32>) -> Vec<u32> {
+    while mag.last() == Some(&0) {
+        mag.pop();
+    }
+    mag
+}
+
+fn bigint_is_zero(b: &BigIntVal) -> bool {
+    b.mag.is_empty()
+}
+
+fn bigint_from_i64(n: i64) -> BigIntVal {
+    let positive = n >= 0;
+    let mut u = n.unsigned_abs() as u128;
+    let mut mag = Vec::new();
+    while u > 0 {
+        mag.push((u & 0xFFFF_FFFF) as u32);
+        u >>= 32;
+    }
+    BigIntVal { positive, mag }
+}
+
+fn bigint_to_i64(b: &BigIntVal) -> Option<i64> {
+    if b.mag.len() > 2 {
+        return None;
+    }
+    let mut val: u128 = 0;
+    for (i, &limb) in b.mag.iter().enumerate() {
+        val |= (limb as u128) << (32 * i);
+    }
+    if b.positive {
+        if val <= i64::MAX as u128 {
+            Some(val as i64)
+        } else {
+            None
+        }
+    } else if val <= (i64::MAX as u128) + 1 {
+        Some(-(val as i128) as i64)
+    } else {
+        None
+    }
+}
+
+fn bigint_to_f64(b: &BigIntVal) -> f64 {
+    let mut val = 0f64;
+    for &limb in b.mag.iter().rev() {
+        val = val * 4294967296.0 + limb as f64;
+    }
+    if b.positive {
+        val
+    } else {
+        -val
+    }
+}
+
+fn bigint_to_string(b: &BigIntVal) -> String {
+    if bigint_is_zero(b) {
+        return "0".to_string();
+    }
+    let mut mag = b.mag.clone();
+    let mut digits = Vec::new();
+    while !mag.is_empty() {
+        let mut rem: u64 = 0;
+        let mut new_mag = vec![0u32; mag.len()];
+        for i in (0..mag.len()).rev() {
+            let cur = (rem << 32) | mag[i] as u64;
+            new_mag[i] = (cur / 10) as u32;
+            rem = cur % 10;
+        }
+        digits.push((b'0' + rem as u8) as char);
+        mag = bigint_normalize(new_mag);
+    }
+    let mut s: String = digits.into_iter().rev().collect();
+    if !b.positive {
+        s.insert(0, '-');
+    }
+    s
+}
+
+fn bigint_cmp_mag(a: &[u32], b: &[u32]) -> Ordering {
+    if a.len() != b.len() {
+        return a.len().cmp(&b.len());
+    }
+    for i in (0..a.len()).rev() {
+        if a[i] != b[i] {
+            return a[i].cmp(&b[i]);
+        }
+    }
+    Ordering::Equal
+}
+
+fn bigint_cmp(a: &BigIntVal, b: &BigIntVal) -> Ordering {
+    match (a.positive, b.positive) {
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        (true, true) => bigint_cmp_mag(&a.mag, &b.mag),
+        (false, false) => bigint_cmp_mag(&b.mag, &a.mag),
+    }
+}
+
+fn bigint_add_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry: u64 = 0;
+    for i in 0..a.len().max(b.len()) {
+        let x = *a.get(i).unwrap_or(&0) as u64;
+        let y = *b.get(i).unwrap_or(&0) as u64;
+        let sum = x + y + carry;
+        result.push((sum & 0xFFFF_FFFF) as u32);
+        carry = sum >> 32;
+    }
+    if carry > 0 {
+        result.push(carry as u32);
+    }
+    bigint_normalize(result)
+}
+
+/// Requires `a >= b` as magnitudes.
+fn bigint_sub_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len());
+    let mut borrow: i64 = 0;
+    for i in 0..a.len() {
+        let x = a[i] as i64;
+        let y = *b.get(i).unwrap_or(&0) as i64;
+        let mut diff = x - y - borrow;
+        if diff < 0 {
+            diff += 1i64 << 32;
+            borrow = 1;
+        } else {
+            borrow = 0;
+        }
+        result.push(diff as u32);
+    }
+    bigint_normalize(result)
+}
+
+fn bigint_negate(a: &BigIntVal) -> BigIntVal {
+    if bigint_is_zero(a) {
+        a.clone()
+    } else {
+        BigIntVal { positive: !a.positive, mag: a.mag.clone() }
+    }
+}
+
+fn bigint_add(a: &BigIntVal, b: &BigIntVal) -> BigIntVal {
+    if a.positive == b.positive {
+        BigIntVal { positive: a.positive, mag: bigint_add_mag(&a.mag, &b.mag) }
+    } else {
+        match bigint_cmp_mag(&a.mag, &b.mag) {
+            Ordering::Equal => BigIntVal { positive: true, mag: vec![] },
+            Ordering::Greater => BigIntVal { positive: a.positive, mag: bigint_sub_mag(&a.mag, &b.mag) },
+            Ordering::Less => BigIntVal { positive: b.positive, mag: bigint_sub_mag(&b.mag, &a.mag) },
+        }
+    }
+}
+
+fn bigint_sub(a: &BigIntVal, b: &BigIntVal) -> BigIntVal {
+    bigint_add(a, &bigint_negate(b))
+}
+
+fn bigint_mul(a: &BigIntVal, b: &BigIntVal) -> BigIntVal {
+    if bigint_is_zero(a) || bigint_is_zero(b) {
+        return BigIntVal { positive: true, mag: vec![] };
+    }
+    let mut result = vec![0u32; a.mag.len() + b.mag.len()];
+    for (i, &x) in a.mag.iter().enumerate() {
+        let mut carry: u64 = 0;
+        for (j, &y) in b.mag.iter().enumerate() {
+            let idx = i + j;
+            let prod = x as u64 * y as u64 + result[idx] as u64 + carry;
+            result[idx] = (prod & 0xFFFF_FFFF) as u32;
+            carry = prod >> 32;
+        }
+        let mut k = i + b.mag.len();
+        while carry > 0 {
+            let sum = result[k] as u64 + carry;
+            result[k] = (sum & 0xFFFF_FFFF) as u32;
+            carry = sum >> 32;
+            k += 1;
+        }
+    }
+    BigIntVal { positive: a.positive == b.positive, mag: bigint_normalize(result) }
+}
+
+fn bigint_shl1(mag: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(mag.len() + 1);
+    let mut carry = 0u32;
+    for &limb in mag {
+        result.push((limb << 1) | carry);
+        carry = limb >> 31;
+    }
+    if carry > 0 {
+        result.push(carry);
+    }
+    bigint_normalize(result)
+}
+
+/// Schoolbook bit-by-bit long division of unsigned magnitudes.
+fn bigint_divmod_mag(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if b.is_empty() {
+        panic!("division by zero");
+    }
+    if bigint_cmp_mag(a, b) == Ordering::Less {
+        return (vec![], a.to_vec());
+    }
+    let bits = a.len() * 32;
+    let mut quotient = vec![0u32; a.len()];
+    let mut remainder: Vec<u32> = vec![];
+    for i in (0..bits).rev() {
+        remainder = bigint_shl1(&remainder);
+        let (limb, bit) = (i / 32, i % 32);
+        if limb < a.len() && (a[limb] >> bit) & 1 == 1 {
+            if remainder.is_empty() {
+                remainder = vec![1];
+            } else {
+                remainder[0] |= 1;
+            }
+        }
+        if bigint_cmp_mag(&remainder, b) != Ordering::Less {
+            remainder = bigint_sub_mag(&remainder, b);
+            quotient[i / 32] |= 1 << (i % 32);
+        }
+    }
+    (bigint_normalize(quotient), bigint_normalize(remainder))
+}
+
+/// Signed floor division + modulo matching Python's sign-of-divisor rule.
+fn bigint_divmod(a: &BigIntVal, b: &BigIntVal) -> (BigIntVal, BigIntVal) {
+    if bigint_is_zero(b) {
+        panic!("division by zero");
+    }
+    let (qmag, rmag) = bigint_divmod_mag(&a.mag, &b.mag);
+    let mut q = BigIntVal { positive: a.positive == b.positive, mag: qmag };
+    let mut r = BigIntVal { positive: a.positive, mag: rmag };
+    if !bigint_is_zero(&r) && a.positive != b.positive {
+        q = bigint_sub(&q, &bigint_from_i64(1));
+        r = bigint_add(&r, b);
+    }
+    if bigint_is_zero(&q) {
+        q.positive = true;
+    }
+    if bigint_is_zero(&r) {
+        r.positive = true;
+    }
+    (q, r)
+}
+
+fn bigint_pow(base: &BigIntVal, exp: u64) -> BigIntVal {
+    let mut result = bigint_from_i64(1);
+    let mut b = base.clone();
+    let mut e = exp;
+    while e > 0 {
+        if e & 1 == 1 {
+            result = bigint_mul(&result, &b);
+        }
+        b = bigint_mul(&b, &b);
+        e >>= 1;
+    }
+    result
+}
+
 // ============================================================================
 // Value - The universal type for Core IL
 // ============================================================================
@@ -207,55 +524,225 @@ enum Value {
     None,
     Bool(bool),
     Int(i64),
+    BigInt(BigIntVal),
     Float(f64),
     Str(String),
-    Array(Rc<RefCell<Vec<Value>>>),
-    Tuple(Rc<Vec<Value>>),
-    Map(Rc<RefCell<OrderedMap>>),
-    Set(Rc<RefCell<OrderedSet>>),
-    Record(Rc<RefCell<OrderedMap>>),
-    Deque(Rc<RefCell<VecDeque<Value>>>),
-    Heap(Rc<RefCell<CoreILHeap>>),
+    Array(Arc<Mutex<Vec<Value>>>),
+    Tuple(Arc<Vec<Value>>),
+    Map(Arc<Mutex<OrderedMap>>),
+    Set(Arc<Mutex<OrderedSet>>),
+    Record(Arc<Mutex<OrderedMap>>),
+    Deque(Arc<Mutex<VecDeque<Value>>>),
+    Heap(Arc<Mutex<CoreILHeap>>),
+    Thread(Arc<ThreadHandle>),
+    Lock(Arc<LockState>),
+}
+
+// ============================================================================
+// Threading - `Arc<Mutex<...>>`-backed Value lets generated programs share
+// state across real OS threads the way Python's `threading` module does.
+// ============================================================================
+
+/// Wraps a joinable thread so `Value::Thread` stays `Clone` (the handle can
+/// only be taken out and joined once; later joins panic like a re-joined
+/// Python `Thread`).
+struct ThreadHandle {
+    inner: Mutex<Option<thread::JoinHandle<Value>>>,
+}
+
+impl fmt::Debug for ThreadHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<thread>")
+    }
+}
+
+/// Explicit acquire/release lock (mirrors `threading.Lock`, not Rust's
+/// scope-based `Mutex` guard).
+struct LockState {
+    locked: Mutex<bool>,
+    cv: Condvar,
+}
+
+impl fmt::Debug for LockState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<lock>")
+    }
+}
+
+/// Spawn `callable` on a new OS thread with `args`, returning a `Value::Thread` handle.
+fn coreil_thread_spawn(callable: fn(&[Value]) -> Value, args: Vec<Value>) -> Value {
+    let handle = thread::spawn(move || callable(&args));
+    Value::Thread(Arc::new(ThreadHandle { inner: Mutex::new(Some(handle)) }))
+}
+
+/// Block until the thread finishes and return its result `Value`.
+fn coreil_thread_join(handle: &Value) -> Value {
+    match handle {
+        Value::Thread(h) => {
+            let taken = h.inner.lock().unwrap().take();
+            match taken {
+                Some(join) => join.join().unwrap_or_else(|_| panic!("runtime error: thread raised an exception")),
+                None => panic!("runtime error: cannot join thread twice"),
+            }
+        }
+        _ => panic!("cannot join {}", type_name(handle)),
+    }
+}
+
+fn coreil_lock_new() -> Value {
+    Value::Lock(Arc::new(LockState { locked: Mutex::new(false), cv: Condvar::new() }))
+}
+
+fn coreil_lock_acquire(lock: &Value) {
+    match lock {
+        Value::Lock(l) => {
+            let mut locked = l.locked.lock().unwrap();
+            while *locked {
+                locked = l.cv.wait(locked).unwrap();
+            }
+            *locked = true;
+        }
+        _ => panic!("cannot acquire {}", type_name(lock)),
+    }
+}
+
+fn coreil_lock_release(lock: &Value) {
+    match lock {
+        Value::Lock(l) => {
+            let mut locked = l.locked.lock().unwrap();
+            *locked = false;
+            l.cv.notify_one();
+        }
+        _ => panic!("cannot release {}", type_name(lock)),
+    }
+}
+
+/// Int-like values participate in the arbitrary-precision fast-path/promotion dance.
+fn value_is_int_like(v: &Value) -> bool {
+    matches!(v, Value::Int(_) | Value::Bool(_) | Value::BigInt(_))
+}
+
+fn to_bigint(v: &Value) -> BigIntVal {
+    match v {
+        Value::Int(n) => bigint_from_i64(*n),
+        Value::Bool(b) => bigint_from_i64(if *b { 1 } else { 0 }),
+        Value::BigInt(b) => b.clone(),
+        _ => panic!("cannot convert {} to int", type_name(v)),
+    }
+}
+
+/// Demote a `BigInt` back to a plain `Int` when it fits in `i64`.
+fn demote_bigint(b: BigIntVal) -> Value {
+    match bigint_to_i64(&b) {
+        Some(n) => Value::Int(n),
+        None => Value::BigInt(b),
+    }
 }
 
 // ============================================================================
 // Value Serialization (for map/set keys)
 // ============================================================================
 
-fn serialize_value(v: &Value) -> String {
+/// Only immutable values may be hashed, as with Python dict/set keys: mutable
+/// containers (`Array`, `Map`, `Set`, `Record`, `Deque`, `Heap`) are not, and
+/// neither are `Thread`/`Lock` handles.
+fn hashable(v: &Value) -> bool {
+    !matches!(
+        v,
+        Value::Array(_)
+            | Value::Map(_)
+            | Value::Set(_)
+            | Value::Record(_)
+            | Value::Deque(_)
+            | Value::Heap(_)
+            | Value::Thread(_)
+            | Value::Lock(_)
+    )
+}
+
+fn check_hashable(v: &Value) {
+    if !hashable(v) {
+        panic!("unhashable type: '{}'", type_name(v));
+    }
+}
+
+fn fnv_mix(h: &mut u64, byte: u8) {
+    *h ^= byte as u64;
+    *h = h.wrapping_mul(0x100000001b3);
+}
+
+fn fnv_mix_bytes(h: &mut u64, bytes: &[u8]) {
+    for &b in bytes {
+        fnv_mix(h, b);
+    }
+}
+
+/// Structural FNV-1a hash over the hashable subset of `Value`. `Int`,
+/// `BigInt`, `Bool`, and `Float` are folded through the same numeric
+/// encoding so that `hash(1) == hash(1.0)`, matching Python.
+fn hash_value(v: &Value) -> u64 {
+    let mut h: u64 = 0xcbf29ce484222325; // FNV-1a offset basis
     match v {
-        Value::None => "N".to_string(),
-        Value::Bool(b) => {
-            if *b {
-                "B1".to_string()
-            } else {
-                "B0".to_string()
+        Value::None => fnv_mix(&mut h, 0),
+        Value::Bool(_) | Value::Int(_) | Value::BigInt(_) | Value::Float(_) => {
+            fnv_mix(&mut h, 1);
+            let mut f = to_numeric(v);
+            if f == 0.0 {
+                f = 0.0; // normalize -0.0 so it collides with 0
             }
+            fnv_mix_bytes(&mut h, &f.to_bits().to_le_bytes());
         }
-        Value::Int(n) => format!("I{}", n),
-        Value::Float(f) => format!("F{:.17}", f),
-        Value::Str(s) => format!("S{}:{}", s.len(), s),
-        Value::Tuple(items) => {
-            let parts: Vec<String> = items.iter().map(|v| serialize_value(v)).collect();
-            format!("T({})", parts.join(","))
+        Value::Str(s) => {
+            fnv_mix(&mut h, 2);
+            fnv_mix_bytes(&mut h, s.as_bytes());
         }
-        Value::Array(arr) => {
-            let arr = arr.borrow();
-            let parts: Vec<String> = arr.iter().map(|v| serialize_value(v)).collect();
-            format!("A[{}]", parts.join(","))
+        Value::Tuple(items) => {
+            fnv_mix(&mut h, 3);
+            for item in items.iter() {
+                fnv_mix_bytes(&mut h, &hash_value(item).to_le_bytes());
+            }
         }
-        Value::Map(_) => format!("M@{:p}", Rc::as_ptr(match v { Value::Map(m) => m, _ => unreachable!() })),
-        Value::Set(_) => format!("SET@{:p}", Rc::as_ptr(match v { Value::Set(s) => s, _ => unreachable!() })),
-        Value::Record(_) => format!("R@{:p}", Rc::as_ptr(match v { Value::Record(r) => r, _ => unreachable!() })),
-        Value::Deque(_) => format!("DQ@{:p}", Rc::as_ptr(match v { Value::Deque(d) => d, _ => unreachable!() })),
-        Value::Heap(_) => format!("H@{:p}", Rc::as_ptr(match v { Value::Heap(h) => h, _ => unreachable!() })),
+        _ => panic!("unhashable type: '{}'", type_name(v)),
     }
+    h
 }
 
 // ============================================================================
 // Python-compatible formatting
 // ============================================================================
 
+thread_local! {
+    // Pointers to containers currently being formatted on this thread, so a
+    // container that (directly or transitively) contains itself is detected
+    // before recursing back into its still-locked `Mutex` — which would
+    // deadlock forever, unlike the `already borrowed` panic `RefCell` gave us
+    // before the `Arc<Mutex<_>>` switch.
+    static FORMAT_STACK: std::cell::RefCell<Vec<*const ()>> = std::cell::RefCell::new(Vec::new());
+}
+
+/// RAII guard that pops `FORMAT_STACK` on drop, so a panic while formatting a
+/// container's contents doesn't leave a stale entry behind.
+struct FormatGuard;
+
+impl Drop for FormatGuard {
+    fn drop(&mut self) {
+        FORMAT_STACK.with(|stack| { stack.borrow_mut().pop(); });
+    }
+}
+
+/// Runs `body` to format a container's contents, short-circuiting to
+/// `ellipsis` (Python's own cyclic-repr marker, e.g. `"[...]"`) if `ptr` is
+/// already being formatted further up the call stack.
+fn format_container(ptr: *const (), ellipsis: &str, body: impl FnOnce() -> String) -> String {
+    let already_visited = FORMAT_STACK.with(|stack| stack.borrow().contains(&ptr));
+    if already_visited {
+        return ellipsis.to_string();
+    }
+    FORMAT_STACK.with(|stack| stack.borrow_mut().push(ptr));
+    let _guard = FormatGuard;
+    body()
+}
+
 /// Format a value for display inside a container (strings get single-quoted)
 fn format_value_repr(v: &Value) -> String {
     match v {
@@ -276,13 +763,14 @@ fn format_value(v: &Value) -> String {
             }
         }
         Value::Int(n) => format!("{}", n),
+        Value::BigInt(b) => bigint_to_string(b),
         Value::Float(f) => format_float(*f),
         Value::Str(s) => s.clone(),
-        Value::Array(arr) => {
-            let arr = arr.borrow();
+        Value::Array(arr) => format_container(Arc::as_ptr(arr) as *const (), "[...]", || {
+            let arr = arr.lock().unwrap();
             let parts: Vec<String> = arr.iter().map(|v| format_value_repr(v)).collect();
             format!("[{}]", parts.join(", "))
-        }
+        }),
         Value::Tuple(items) => {
             let parts: Vec<String> = items.iter().map(|v| format_value_repr(v)).collect();
             if items.len() == 1 {
@@ -291,26 +779,26 @@ fn format_value(v: &Value) -> String {
                 format!("({})", parts.join(", "))
             }
         }
-        Value::Map(map) => {
-            let map = map.borrow();
+        Value::Map(map) => format_container(Arc::as_ptr(map) as *const (), "{...}", || {
+            let map = map.lock().unwrap();
             let parts: Vec<String> = map
                 .entries
                 .iter()
                 .map(|(k, v)| format!("{}: {}", format_value_repr(k), format_value_repr(v)))
                 .collect();
             format!("{{{}}}", parts.join(", "))
-        }
-        Value::Set(set) => {
-            let set = set.borrow();
+        }),
+        Value::Set(set) => format_container(Arc::as_ptr(set) as *const (), "{...}", || {
+            let set = set.lock().unwrap();
             if set.items.is_empty() {
                 "set()".to_string()
             } else {
                 let parts: Vec<String> = set.items.iter().map(|v| format_value_repr(v)).collect();
                 format!("{{{}}}", parts.join(", "))
             }
-        }
-        Value::Record(rec) => {
-            let rec = rec.borrow();
+        }),
+        Value::Record(rec) => format_container(Arc::as_ptr(rec) as *const (), "{...}", || {
+            let rec = rec.lock().unwrap();
             let parts: Vec<String> = rec
                 .entries
                 .iter()
@@ -320,13 +808,15 @@ fn format_value(v: &Value) -> String {
                 })
                 .collect();
             format!("{{{}}}", parts.join(", "))
-        }
-        Value::Deque(dq) => {
-            let dq = dq.borrow();
+        }),
+        Value::Deque(dq) => format_container(Arc::as_ptr(dq) as *const (), "deque([...])", || {
+            let dq = dq.lock().unwrap();
             let parts: Vec<String> = dq.iter().map(|v| format_value_repr(v)).collect();
             format!("deque([{}])", parts.join(", "))
-        }
+        }),
         Value::Heap(_) => "<heap>".to_string(),
+        Value::Thread(_) => "<thread>".to_string(),
+        Value::Lock(_) => "<lock>".to_string(),
     }
 }
 
@@ -376,15 +866,18 @@ fn is_truthy(v: &Value) -> bool {
         Value::None => false,
         Value::Bool(b) => *b,
         Value::Int(n) => *n != 0,
+        Value::BigInt(b) => !bigint_is_zero(b),
         Value::Float(f) => *f != 0.0,
         Value::Str(s) => !s.is_empty(),
-        Value::Array(arr) => !arr.borrow().is_empty(),
+        Value::Array(arr) => !arr.lock().unwrap().is_empty(),
         Value::Tuple(items) => !items.is_empty(),
-        Value::Map(map) => !map.borrow().entries.is_empty(),
-        Value::Set(set) => !set.borrow().items.is_empty(),
+        Value::Map(map) => !map.lock().unwrap().entries.is_empty(),
+        Value::Set(set) => !set.lock().unwrap().items.is_empty(),
         Value::Record(_) => true,
-        Value::Deque(dq) => !dq.borrow().is_empty(),
-        Value::Heap(h) => h.borrow().size() > 0,
+        Value::Deque(dq) => !dq.lock().unwrap().is_empty(),
+        Value::Heap(h) => h.lock().unwrap().size() > 0,
+        Value::Thread(_) => true,
+        Value::Lock(_) => true,
     }
 }
 
@@ -399,6 +892,7 @@ fn logical_not(v: &Value) -> Value {
 fn as_int(v: &Value) -> i64 {
     match v {
         Value::Int(n) => *n,
+        Value::BigInt(b) => bigint_to_i64(b).unwrap_or_else(|| bigint_to_f64(b) as i64),
         Value::Float(f) => *f as i64,
         Value::Bool(b) => {
             if *b {
@@ -420,6 +914,7 @@ fn as_int(v: &Value) -> i64 {
 fn as_float(v: &Value) -> f64 {
     match v {
         Value::Int(n) => *n as f64,
+        Value::BigInt(b) => bigint_to_f64(b),
         Value::Float(f) => *f,
         Value::Bool(b) => {
             if *b {
@@ -452,6 +947,7 @@ fn type_name(v: &Value) -> &'static str {
         Value::None => "None",
         Value::Bool(_) => "bool",
         Value::Int(_) => "int",
+        Value::BigInt(_) => "int",
         Value::Float(_) => "float",
         Value::Str(_) => "str",
         Value::Array(_) => "list",
@@ -461,6 +957,8 @@ fn type_name(v: &Value) -> &'static str {
         Value::Record(_) => "record",
         Value::Deque(_) => "deque",
         Value::Heap(_) => "heap",
+        Value::Thread(_) => "thread",
+        Value::Lock(_) => "lock",
     }
 }
 
@@ -472,6 +970,7 @@ fn type_name(v: &Value) -> &'static str {
 fn to_numeric(v: &Value) -> f64 {
     match v {
         Value::Int(n) => *n as f64,
+        Value::BigInt(b) => bigint_to_f64(b),
         Value::Float(f) => *f,
         Value::Bool(b) => {
             if *b {
@@ -484,9 +983,9 @@ fn to_numeric(v: &Value) -> f64 {
     }
 }
 
-/// Check if a value is numeric (int, float, or bool)
+/// Check if a value is numeric (int, float, bigint, or bool)
 fn is_numeric(v: &Value) -> bool {
-    matches!(v, Value::Int(_) | Value::Float(_) | Value::Bool(_))
+    matches!(v, Value::Int(_) | Value::BigInt(_) | Value::Float(_) | Value::Bool(_))
 }
 
 /// Return an integer result if both operands are integer-compatible, otherwise float
@@ -514,15 +1013,31 @@ fn op_add(a: &Value, b: &Value) -> Value {
         (other, Value::Str(s)) => Value::Str(format!("{}{}", format_value(other), s)),
         // Array concatenation
         (Value::Array(a1), Value::Array(a2)) => {
-            let mut result = a1.borrow().clone();
-            result.extend(a2.borrow().iter().cloned());
-            Value::Array(Rc::new(RefCell::new(result)))
+            let mut result = a1.lock().unwrap().clone();
+            result.extend(a2.lock().unwrap().iter().cloned());
+            Value::Array(Arc::new(Mutex::new(result)))
+        }
+        _ if value_is_int_like(a) && value_is_int_like(b) => {
+            if let (Value::Int(x), Value::Int(y)) = (a, b) {
+                if let Some(v) = x.checked_add(*y) {
+                    return Value::Int(v);
+                }
+            }
+            demote_bigint(bigint_add(&to_bigint(a), &to_bigint(b)))
         }
         _ => numeric_result(a, b, |x, y| x + y, |x, y| x + y),
     }
 }
 
 fn op_subtract(a: &Value, b: &Value) -> Value {
+    if value_is_int_like(a) && value_is_int_like(b) {
+        if let (Value::Int(x), Value::Int(y)) = (a, b) {
+            if let Some(v) = x.checked_sub(*y) {
+                return Value::Int(v);
+            }
+        }
+        return demote_bigint(bigint_sub(&to_bigint(a), &to_bigint(b)));
+    }
     numeric_result(a, b, |x, y| x - y, |x, y| x - y)
 }
 
@@ -543,6 +1058,14 @@ fn op_multiply(a: &Value, b: &Value) -> Value {
                 Value::Str(s.repeat(*n as usize))
             }
         }
+        _ if value_is_int_like(a) && value_is_int_like(b) => {
+            if let (Value::Int(x), Value::Int(y)) = (a, b) {
+                if let Some(v) = x.checked_mul(*y) {
+                    return Value::Int(v);
+                }
+            }
+            demote_bigint(bigint_mul(&to_bigint(a), &to_bigint(b)))
+        }
         _ => numeric_result(a, b, |x, y| x * y, |x, y| x * y),
     }
 }
@@ -568,6 +1091,10 @@ fn op_floor_divide(a: &Value, b: &Value) -> Value {
             let result = (*x as f64 / *y as f64).floor() as i64;
             Value::Int(result)
         }
+        _ if value_is_int_like(a) && value_is_int_like(b) => {
+            let (q, _) = bigint_divmod(&to_bigint(a), &to_bigint(b));
+            demote_bigint(q)
+        }
         _ => {
             let fa = to_numeric(a);
             let fb = to_numeric(b);
@@ -588,6 +1115,10 @@ fn op_modulo(a: &Value, b: &Value) -> Value {
             }
             Value::Int(x.rem_euclid(*y))
         }
+        _ if value_is_int_like(a) && value_is_int_like(b) => {
+            let (_, r) = bigint_divmod(&to_bigint(a), &to_bigint(b));
+            demote_bigint(r)
+        }
         _ => {
             let fa = to_numeric(a);
             let fb = to_numeric(b);
@@ -603,12 +1134,17 @@ fn op_modulo(a: &Value, b: &Value) -> Value {
 
 fn op_power(a: &Value, b: &Value) -> Value {
     match (a, b) {
-        (Value::Int(base), Value::Int(exp)) => {
-            if *exp >= 0 {
-                Value::Int(base.pow(*exp as u32))
-            } else {
-                Value::Float((*base as f64).powf(*exp as f64))
+        (Value::Int(base), Value::Int(exp)) if *exp >= 0 => {
+            if *exp <= u32::MAX as i64 {
+                if let Some(v) = base.checked_pow(*exp as u32) {
+                    return Value::Int(v);
+                }
             }
+            demote_bigint(bigint_pow(&bigint_from_i64(*base), *exp as u64))
+        }
+        (Value::Int(base), Value::Int(exp)) => Value::Float((*base as f64).powf(*exp as f64)),
+        (a, Value::Int(exp)) if value_is_int_like(a) && *exp >= 0 => {
+            demote_bigint(bigint_pow(&to_bigint(a), *exp as u64))
         }
         _ => Value::Float(to_numeric(a).powf(to_numeric(b))),
     }
@@ -636,10 +1172,19 @@ fn values_equal(a: &Value, b: &Value) -> bool {
         (Value::Int(x), Value::Bool(y)) => *x == (if *y { 1i64 } else { 0i64 }),
         (Value::Bool(x), Value::Float(y)) => (if *x { 1.0 } else { 0.0 }) == *y,
         (Value::Float(x), Value::Bool(y)) => *x == (if *y { 1.0 } else { 0.0 }),
+        // BigInt comparisons (cross-type with int/bool/float)
+        (Value::BigInt(_), _) | (_, Value::BigInt(_)) if value_is_int_like(a) && value_is_int_like(b) => {
+            bigint_cmp(&to_bigint(a), &to_bigint(b)) == Ordering::Equal
+        }
+        (Value::BigInt(x), Value::Float(y)) => bigint_to_f64(x) == *y,
+        (Value::Float(x), Value::BigInt(y)) => *x == bigint_to_f64(y),
         // Array deep comparison
         (Value::Array(a1), Value::Array(a2)) => {
-            let a1 = a1.borrow();
-            let a2 = a2.borrow();
+            if Arc::ptr_eq(a1, a2) {
+                return true;
+            }
+            let a1 = a1.lock().unwrap().clone();
+            let a2 = a2.lock().unwrap();
             if a1.len() != a2.len() {
                 return false;
             }
@@ -654,24 +1199,29 @@ fn values_equal(a: &Value, b: &Value) -> bool {
         }
         // Map deep comparison
         (Value::Map(m1), Value::Map(m2)) => {
-            let m1 = m1.borrow();
-            let m2 = m2.borrow();
-            if m1.entries.len() != m2.entries.len() {
+            if Arc::ptr_eq(m1, m2) {
+                return true;
+            }
+            let m1 = m1.lock().unwrap().entries.clone();
+            let m2 = m2.lock().unwrap();
+            if m1.len() != m2.entries.len() {
                 return false;
             }
-            m1.entries
-                .iter()
+            m1.iter()
                 .zip(m2.entries.iter())
                 .all(|((k1, v1), (k2, v2))| values_equal(k1, k2) && values_equal(v1, v2))
         }
         // Set comparison (order-independent)
         (Value::Set(s1), Value::Set(s2)) => {
-            let s1 = s1.borrow();
-            let s2 = s2.borrow();
-            if s1.items.len() != s2.items.len() {
+            if Arc::ptr_eq(s1, s2) {
+                return true;
+            }
+            let s1 = s1.lock().unwrap().items.clone();
+            let s2 = s2.lock().unwrap();
+            if s1.len() != s2.items.len() {
                 return false;
             }
-            s1.items.iter().all(|item| s2.has(item))
+            s1.iter().all(|item| s2.has(item))
         }
         _ => false,
     }
@@ -692,6 +1242,11 @@ fn compare_values(a: &Value, b: &Value) -> Ordering {
         (Value::Float(x), Value::Int(y)) => x
             .partial_cmp(&(*y as f64))
             .unwrap_or(Ordering::Equal),
+        (Value::BigInt(x), Value::BigInt(y)) => bigint_cmp(x, y),
+        (Value::BigInt(x), Value::Int(y)) => bigint_cmp(x, &bigint_from_i64(*y)),
+        (Value::Int(x), Value::BigInt(y)) => bigint_cmp(&bigint_from_i64(*x), y),
+        (Value::BigInt(x), Value::Float(y)) => bigint_to_f64(x).partial_cmp(y).unwrap_or(Ordering::Equal),
+        (Value::Float(x), Value::BigInt(y)) => x.partial_cmp(&bigint_to_f64(y)).unwrap_or(Ordering::Equal),
         (Value::Bool(x), other) if is_numeric(other) => {
             let xn = if *x { 1i64 } else { 0 };
             compare_values(&Value::Int(xn), other)
@@ -707,6 +1262,16 @@ fn compare_values(a: &Value, b: &Value) -> Ordering {
         }
         // String comparison
         (Value::Str(x), Value::Str(y)) => x.cmp(y),
+        // Element-wise lexicographic comparison, like Python's list/tuple `<`
+        (Value::Array(x), Value::Array(y)) => {
+            if Arc::ptr_eq(x, y) {
+                return Ordering::Equal;
+            }
+            let x = x.lock().unwrap().clone();
+            let y = y.lock().unwrap();
+            compare_sequences(x.iter(), y.iter())
+        }
+        (Value::Tuple(x), Value::Tuple(y)) => compare_sequences(x.iter(), y.iter()),
         _ => panic!(
             "cannot compare {} and {}",
             type_name(a),
@@ -715,6 +1280,26 @@ fn compare_values(a: &Value, b: &Value) -> Ordering {
     }
 }
 
+/// Python's sequence ordering: compare elements pairwise, first
+/// non-`Equal` result wins; a shorter prefix of an otherwise-equal
+/// sequence is `Less`.
+fn compare_sequences<'a>(
+    mut xs: impl Iterator<Item = &'a Value>,
+    mut ys: impl Iterator<Item = &'a Value>,
+) -> Ordering {
+    loop {
+        match (xs.next(), ys.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) => match compare_values(x, y) {
+                Ordering::Equal => continue,
+                ord => return ord,
+            },
+        }
+    }
+}
+
 fn op_less_than(a: &Value, b: &Value) -> Value {
     Value::Bool(compare_values(a, b) == Ordering::Less)
 }
@@ -736,7 +1321,7 @@ fn op_greater_than_or_equal(a: &Value, b: &Value) -> Value {
 // ============================================================================
 
 fn make_array(items: Vec<Value>) -> Value {
-    Value::Array(Rc::new(RefCell::new(items)))
+    Value::Array(Arc::new(Mutex::new(items)))
 }
 
 /// Resolve a Python-style index (supports negative indexing)
@@ -754,7 +1339,7 @@ fn resolve_index(idx: i64, len: usize) -> usize {
 fn array_index(arr: &Value, index: &Value) -> Value {
     match arr {
         Value::Array(a) => {
-            let a = a.borrow();
+            let a = a.lock().unwrap();
             let idx = as_int(index);
             let resolved = resolve_index(idx, a.len());
             a[resolved].clone()
@@ -777,7 +1362,7 @@ fn array_index(arr: &Value, index: &Value) -> Value {
 fn array_set_index(arr: &Value, index: &Value, value: Value) {
     match arr {
         Value::Array(a) => {
-            let mut a = a.borrow_mut();
+            let mut a = a.lock().unwrap();
             let idx = as_int(index);
             let resolved = resolve_index(idx, a.len());
             a[resolved] = value;
@@ -789,7 +1374,7 @@ fn array_set_index(arr: &Value, index: &Value, value: Value) {
 fn array_push(arr: &Value, value: Value) {
     match arr {
         Value::Array(a) => {
-            a.borrow_mut().push(value);
+            a.lock().unwrap().push(value);
         }
         _ => panic!("cannot push to {}", type_name(arr)),
     }
@@ -797,10 +1382,10 @@ fn array_push(arr: &Value, value: Value) {
 
 fn array_length(v: &Value) -> Value {
     match v {
-        Value::Array(a) => Value::Int(a.borrow().len() as i64),
+        Value::Array(a) => Value::Int(a.lock().unwrap().len() as i64),
         Value::Str(s) => Value::Int(s.chars().count() as i64),
         Value::Tuple(items) => Value::Int(items.len() as i64),
-        Value::Map(m) => Value::Int(m.borrow().size() as i64),
+        Value::Map(m) => Value::Int(m.lock().unwrap().size() as i64),
         _ => panic!("cannot get length of {}", type_name(v)),
     }
 }
@@ -808,7 +1393,7 @@ fn array_length(v: &Value) -> Value {
 fn array_slice(arr: &Value, start: &Value, end: &Value) -> Value {
     match arr {
         Value::Array(a) => {
-            let a = a.borrow();
+            let a = a.lock().unwrap();
             let len = a.len() as i64;
             let s = resolve_slice_index(as_int(start), len);
             let e = resolve_slice_index(as_int(end), len);
@@ -846,12 +1431,102 @@ fn resolve_slice_index(idx: i64, len: i64) -> usize {
     }
 }
 
+// ============================================================================
+// Full Python slicing (start:stop:step) for arrays and strings
+// ============================================================================
+
+/// Descriptor for `a[start:stop:step]`; `None` means "use the Python default".
+#[derive(Clone, Debug)]
+struct Slice {
+    start: Option<i64>,
+    stop: Option<i64>,
+    step: Option<i64>,
+}
+
+fn make_slice(start: &Value, stop: &Value, step: &Value) -> Slice {
+    let to_opt = |v: &Value| match v {
+        Value::None => None,
+        _ => Some(as_int(v)),
+    };
+    Slice { start: to_opt(start), stop: to_opt(stop), step: to_opt(step) }
+}
+
+/// CPython's `slice.indices(len)`: resolve `(start, stop, step)` into
+/// concrete walk bounds, accounting for the direction of `step`.
+fn slice_bounds(s: &Slice, len: i64) -> (i64, i64, i64) {
+    let step = s.step.unwrap_or(1);
+    if step == 0 {
+        panic!("runtime error: slice step cannot be zero");
+    }
+    if step > 0 {
+        let clamp = |v: i64| -> i64 {
+            let v = if v < 0 { v + len } else { v };
+            v.clamp(0, len)
+        };
+        let start = s.start.map(clamp).unwrap_or(0);
+        let stop = s.stop.map(clamp).unwrap_or(len);
+        (start, stop, step)
+    } else {
+        let clamp = |v: i64| -> i64 {
+            let v = if v < 0 { v + len } else { v };
+            v.clamp(-1, len - 1)
+        };
+        let start = s.start.map(clamp).unwrap_or(len - 1);
+        let stop = s.stop.map(clamp).unwrap_or(-1);
+        (start, stop, step)
+    }
+}
+
+fn array_slice_step(arr: &Value, slice: &Slice) -> Value {
+    match arr {
+        Value::Array(a) => {
+            let a = a.lock().unwrap();
+            let (start, stop, step) = slice_bounds(slice, a.len() as i64);
+            let mut result = Vec::new();
+            let mut i = start;
+            while if step > 0 { i < stop } else { i > stop } {
+                result.push(a[i as usize].clone());
+                i += step;
+            }
+            make_array(result)
+        }
+        _ => panic!("cannot slice {}", type_name(arr)),
+    }
+}
+
+fn str_slice_step(s: &Value, slice: &Slice) -> Value {
+    match s {
+        Value::Str(st) => {
+            let chars: Vec<char> = st.chars().collect();
+            let (start, stop, step) = slice_bounds(slice, chars.len() as i64);
+            let mut result = String::new();
+            let mut i = start;
+            while if step > 0 { i < stop } else { i > stop } {
+                result.push(chars[i as usize]);
+                i += step;
+            }
+            Value::Str(result)
+        }
+        _ => panic!("cannot slice {}", type_name(s)),
+    }
+}
+
+/// `a[start:stop:step]` builtin entry point (any bound may be `Value::None`).
+fn array_slice_val(arr: &Value, start: &Value, stop: &Value, step: &Value) -> Value {
+    array_slice_step(arr, &make_slice(start, stop, step))
+}
+
+/// `s[start:stop:step]` builtin entry point (any bound may be `Value::None`).
+fn str_slice_val(s: &Value, start: &Value, stop: &Value, step: &Value) -> Value {
+    str_slice_step(s, &make_slice(start, stop, step))
+}
+
 // ============================================================================
 // Tuple Operations
 // ============================================================================
 
 fn make_tuple(items: Vec<Value>) -> Value {
-    Value::Tuple(Rc::new(items))
+    Value::Tuple(Arc::new(items))
 }
 
 // ============================================================================
@@ -863,13 +1538,13 @@ fn make_map(pairs: Vec<(Value, Value)>) -> Value {
     for (k, v) in pairs {
         map.set(k, v);
     }
-    Value::Map(Rc::new(RefCell::new(map)))
+    Value::Map(Arc::new(Mutex::new(map)))
 }
 
 fn map_set(map: &Value, key: Value, value: Value) {
     match map {
         Value::Map(m) => {
-            m.borrow_mut().set(key, value);
+            m.lock().unwrap().set(key, value);
         }
         _ => panic!("cannot set on {}", type_name(map)),
     }
@@ -878,7 +1553,7 @@ fn map_set(map: &Value, key: Value, value: Value) {
 fn map_get(map: &Value, key: &Value) -> Value {
     match map {
         Value::Map(m) => {
-            let m = m.borrow();
+            let m = m.lock().unwrap();
             match m.get(key) {
                 Some(v) => v.clone(),
                 None => panic!("key not found: {}", format_value(key)),
@@ -890,21 +1565,21 @@ fn map_get(map: &Value, key: &Value) -> Value {
 
 fn map_get_default(map: &Value, key: &Value, default: &Value) -> Value {
     match map {
-        Value::Map(m) => m.borrow().get_default(key, default),
+        Value::Map(m) => m.lock().unwrap().get_default(key, default),
         _ => panic!("cannot get_default from {}", type_name(map)),
     }
 }
 
 fn map_keys(map: &Value) -> Value {
     match map {
-        Value::Map(m) => make_array(m.borrow().keys()),
+        Value::Map(m) => make_array(m.lock().unwrap().keys()),
         _ => panic!("cannot get keys of {}", type_name(map)),
     }
 }
 
 fn map_contains(map: &Value, key: &Value) -> Value {
     match map {
-        Value::Map(m) => Value::Bool(m.borrow().contains_key(key)),
+        Value::Map(m) => Value::Bool(m.lock().unwrap().contains_key(key)),
         _ => panic!("cannot check contains on {}", type_name(map)),
     }
 }
@@ -918,13 +1593,13 @@ fn make_record(fields: Vec<(&str, Value)>) -> Value {
     for (name, value) in fields {
         map.set(Value::Str(name.to_string()), value);
     }
-    Value::Record(Rc::new(RefCell::new(map)))
+    Value::Record(Arc::new(Mutex::new(map)))
 }
 
 fn get_field(record: &Value, field: &str) -> Value {
     match record {
         Value::Record(r) => {
-            let r = r.borrow();
+            let r = r.lock().unwrap();
             let key = Value::Str(field.to_string());
             match r.get(&key) {
                 Some(v) => v.clone(),
@@ -938,7 +1613,7 @@ fn get_field(record: &Value, field: &str) -> Value {
 fn set_field(record: &Value, field: &str, value: Value) {
     match record {
         Value::Record(r) => {
-            r.borrow_mut()
+            r.lock().unwrap()
                 .set(Value::Str(field.to_string()), value);
         }
         _ => panic!("cannot set field on {}", type_name(record)),
@@ -954,12 +1629,12 @@ fn make_set(items: Vec<Value>) -> Value {
     for item in items {
         set.add(item);
     }
-    Value::Set(Rc::new(RefCell::new(set)))
+    Value::Set(Arc::new(Mutex::new(set)))
 }
 
 fn set_has(set: &Value, item: &Value) -> Value {
     match set {
-        Value::Set(s) => Value::Bool(s.borrow().has(item)),
+        Value::Set(s) => Value::Bool(s.lock().unwrap().has(item)),
         _ => panic!("cannot check membership on {}", type_name(set)),
     }
 }
@@ -967,7 +1642,7 @@ fn set_has(set: &Value, item: &Value) -> Value {
 fn set_add(set: &Value, item: Value) {
     match set {
         Value::Set(s) => {
-            s.borrow_mut().add(item);
+            s.lock().unwrap().add(item);
         }
         _ => panic!("cannot add to {}", type_name(set)),
     }
@@ -976,7 +1651,7 @@ fn set_add(set: &Value, item: Value) {
 fn set_remove(set: &Value, item: &Value) {
     match set {
         Value::Set(s) => {
-            s.borrow_mut().remove(item);
+            s.lock().unwrap().remove(item);
         }
         _ => panic!("cannot remove from {}", type_name(set)),
     }
@@ -984,7 +1659,7 @@ fn set_remove(set: &Value, item: &Value) {
 
 fn set_size(set: &Value) -> Value {
     match set {
-        Value::Set(s) => Value::Int(s.borrow().size() as i64),
+        Value::Set(s) => Value::Int(s.lock().unwrap().size() as i64),
         _ => panic!("cannot get size of {}", type_name(set)),
     }
 }
@@ -994,13 +1669,13 @@ fn set_size(set: &Value) -> Value {
 // ============================================================================
 
 fn deque_new() -> Value {
-    Value::Deque(Rc::new(RefCell::new(VecDeque::new())))
+    Value::Deque(Arc::new(Mutex::new(VecDeque::new())))
 }
 
 fn deque_push_back(dq: &Value, value: Value) {
     match dq {
         Value::Deque(d) => {
-            d.borrow_mut().push_back(value);
+            d.lock().unwrap().push_back(value);
         }
         _ => panic!("cannot push_back on {}", type_name(dq)),
     }
@@ -1009,7 +1684,7 @@ fn deque_push_back(dq: &Value, value: Value) {
 fn deque_push_front(dq: &Value, value: Value) {
     match dq {
         Value::Deque(d) => {
-            d.borrow_mut().push_front(value);
+            d.lock().unwrap().push_front(value);
         }
         _ => panic!("cannot push_front on {}", type_name(dq)),
     }
@@ -1018,7 +1693,7 @@ fn deque_push_front(dq: &Value, value: Value) {
 fn deque_pop_front(dq: &Value) -> Value {
     match dq {
         Value::Deque(d) => d
-            .borrow_mut()
+            .lock().unwrap()
             .pop_front()
             .unwrap_or_else(|| panic!("pop from empty deque")),
         _ => panic!("cannot pop_front on {}", type_name(dq)),
@@ -1028,7 +1703,7 @@ fn deque_pop_front(dq: &Value) -> Value {
 fn deque_pop_back(dq: &Value) -> Value {
     match dq {
         Value::Deque(d) => d
-            .borrow_mut()
+            .lock().unwrap()
             .pop_back()
             .unwrap_or_else(|| panic!("pop from empty deque")),
         _ => panic!("cannot pop_back on {}", type_name(dq)),
@@ -1037,7 +1712,7 @@ fn deque_pop_back(dq: &Value) -> Value {
 
 fn deque_size(dq: &Value) -> Value {
     match dq {
-        Value::Deque(d) => Value::Int(d.borrow().len() as i64),
+        Value::Deque(d) => Value::Int(d.lock().unwrap().len() as i64),
         _ => panic!("cannot get size of {}", type_name(dq)),
     }
 }
@@ -1047,14 +1722,14 @@ fn deque_size(dq: &Value) -> Value {
 // ============================================================================
 
 fn heap_new() -> Value {
-    Value::Heap(Rc::new(RefCell::new(CoreILHeap::new())))
+    Value::Heap(Arc::new(Mutex::new(CoreILHeap::new())))
 }
 
 fn heap_push(heap: &Value, priority: &Value, value: Value) {
     match heap {
         Value::Heap(h) => {
             let p = to_numeric(priority);
-            h.borrow_mut().push(p, value);
+            h.lock().unwrap().push(p, value);
         }
         _ => panic!("cannot push to {}", type_name(heap)),
     }
@@ -1062,21 +1737,21 @@ fn heap_push(heap: &Value, priority: &Value, value: Value) {
 
 fn heap_pop(heap: &Value) -> Value {
     match heap {
-        Value::Heap(h) => h.borrow_mut().pop(),
+        Value::Heap(h) => h.lock().unwrap().pop(),
         _ => panic!("cannot pop from {}", type_name(heap)),
     }
 }
 
 fn heap_peek(heap: &Value) -> Value {
     match heap {
-        Value::Heap(h) => h.borrow().peek(),
+        Value::Heap(h) => h.lock().unwrap().peek(),
         _ => panic!("cannot peek at {}", type_name(heap)),
     }
 }
 
 fn heap_size(heap: &Value) -> Value {
     match heap {
-        Value::Heap(h) => Value::Int(h.borrow().size() as i64),
+        Value::Heap(h) => Value::Int(h.lock().unwrap().size() as i64),
         _ => panic!("cannot get size of {}", type_name(heap)),
     }
 }
@@ -1128,7 +1803,7 @@ fn string_join(separator: &Value, arr: &Value) -> Value {
     };
     match arr {
         Value::Array(a) => {
-            let a = a.borrow();
+            let a = a.lock().unwrap();
             let parts: Vec<String> = a.iter().map(|v| format_value(v)).collect();
             Value::Str(parts.join(&sep))
         }
@@ -1228,6 +1903,7 @@ fn math_ceil(v: &Value) -> Value {
 fn math_abs(v: &Value) -> Value {
     match v {
         Value::Int(n) => Value::Int(n.abs()),
+        Value::BigInt(b) => Value::BigInt(BigIntVal { positive: true, mag: b.mag.clone() }),
         Value::Float(f) => Value::Float(f.abs()),
         _ => Value::Float(to_numeric(v).abs()),
     }
@@ -1263,166 +1939,288 @@ enum JsonToken {
     StringVal(String),
     NumberVal(String),
     True, False, Null,
+    NaN, Infinity, NegInfinity,
 }
 
-struct JsonLexer { chars: Vec<char>, pos: usize }
+/// A JSON syntax error with the 1-based line/column it was detected at.
+struct JsonError { message: String, line: usize, column: usize }
+
+struct JsonLexer { chars: Vec<char>, pos: usize, line: usize, column: usize }
 
 impl JsonLexer {
-    fn new(input: &str) -> Self { JsonLexer { chars: input.chars().collect(), pos: 0 } }
+    fn new(input: &str) -> Self { JsonLexer { chars: input.chars().collect(), pos: 0, line: 1, column: 1 } }
+
+    fn err(&self, message: String) -> JsonError { JsonError { message, line: self.line, column: self.column } }
+
+    fn advance(&mut self) {
+        if self.pos < self.chars.len() {
+            if self.chars[self.pos] == '\n' { self.line += 1; self.column = 1; } else { self.column += 1; }
+            self.pos += 1;
+        }
+    }
 
     fn skip_ws(&mut self) {
-        while self.pos < self.chars.len() && self.chars[self.pos].is_ascii_whitespace() { self.pos += 1; }
+        while self.pos < self.chars.len() && self.chars[self.pos].is_ascii_whitespace() { self.advance(); }
     }
 
-    fn next_token(&mut self) -> Option<JsonToken> {
+    fn next_token(&mut self) -> Result<Option<(JsonToken, usize, usize)>, JsonError> {
         self.skip_ws();
-        if self.pos >= self.chars.len() { return None; }
+        if self.pos >= self.chars.len() { return Ok(None); }
+        let (line, column) = (self.line, self.column);
         let ch = self.chars[self.pos];
-        match ch {
-            '{' => { self.pos += 1; Some(JsonToken::LBrace) }
-            '}' => { self.pos += 1; Some(JsonToken::RBrace) }
-            '[' => { self.pos += 1; Some(JsonToken::LBracket) }
-            ']' => { self.pos += 1; Some(JsonToken::RBracket) }
-            ':' => { self.pos += 1; Some(JsonToken::Colon) }
-            ',' => { self.pos += 1; Some(JsonToken::Comma) }
-            '"' => Some(self.lex_string()),
-            't' => self.lex_kw("true", JsonToken::True),
-            'f' => self.lex_kw("false", JsonToken::False),
-            'n' => self.lex_kw("null", JsonToken::Null),
-            '-' | '0'..='9' => Some(self.lex_number()),
-            _ => panic!("runtime error: invalid JSON: unexpected '{}'", ch),
-        }
-    }
-
-    fn lex_string(&mut self) -> JsonToken {
-        self.pos += 1;
-        let mut s = String::new();
-        while self.pos < self.chars.len() {
+        let tok = match ch {
+            '{' => { self.advance(); JsonToken::LBrace }
+            '}' => { self.advance(); JsonToken::RBrace }
+            '[' => { self.advance(); JsonToken::LBracket }
+            ']' => { self.advance(); JsonToken::RBracket }
+            ':' => { self.advance(); JsonToken::Colon }
+            ',' => { self.advance(); JsonToken::Comma }
+            '"' => self.lex_string()?,
+            't' => self.lex_kw("true", JsonToken::True)?,
+            'f' => self.lex_kw("false", JsonToken::False)?,
+            'n' => self.lex_kw("null", JsonToken::Null)?,
+            'N' => self.lex_kw("NaN", JsonToken::NaN)?,
+            'I' => self.lex_kw("Infinity", JsonToken::Infinity)?,
+            '-' if self.chars.get(self.pos + 1) == Some(&'I') => {
+                self.advance();
+                self.lex_kw("Infinity", JsonToken::NegInfinity)?
+            }
+            '-' | '0'..='9' => self.lex_number(),
+            _ => return Err(self.err(format!("invalid JSON: unexpected '{}'", ch))),
+        };
+        Ok(Some((tok, line, column)))
+    }
+
+    fn read_hex4(&mut self) -> Result<u32, JsonError> {
+        let mut hex = String::new();
+        for _ in 0..4 {
+            self.advance();
+            if self.pos >= self.chars.len() { return Err(self.err("invalid JSON: unterminated escape".to_string())); }
+            hex.push(self.chars[self.pos]);
+        }
+        u32::from_str_radix(&hex, 16).map_err(|_| self.err("invalid JSON: bad \\u escape".to_string()))
+    }
+
+    fn lex_string(&mut self) -> Result<JsonToken, JsonError> {
+        self.advance(); // opening quote
+        let mut s = String::new();
+        while self.pos < self.chars.len() {
             let ch = self.chars[self.pos];
-            if ch == '"' { self.pos += 1; return JsonToken::StringVal(s); }
+            if ch == '"' { self.advance(); return Ok(JsonToken::StringVal(s)); }
             if ch == '\\' {
-                self.pos += 1;
-                if self.pos >= self.chars.len() { panic!("runtime error: invalid JSON: unterminated escape"); }
+                self.advance();
+                if self.pos >= self.chars.len() { return Err(self.err("invalid JSON: unterminated escape".to_string())); }
                 match self.chars[self.pos] {
                     '"' => s.push('"'), '\\' => s.push('\\'), '/' => s.push('/'),
                     'b' => s.push('\u{08}'), 'f' => s.push('\u{0C}'),
                     'n' => s.push('\n'), 'r' => s.push('\r'), 't' => s.push('\t'),
                     'u' => {
-                        let mut hex = String::new();
-                        for _ in 0..4 { self.pos += 1; hex.push(self.chars[self.pos]); }
-                        if let Some(c) = char::from_u32(u32::from_str_radix(&hex, 16).unwrap_or(0)) { s.push(c); }
+                        let hi = self.read_hex4()?;
+                        if (0xD800..=0xDBFF).contains(&hi)
+                            && self.chars.get(self.pos + 1) == Some(&'\\')
+                            && self.chars.get(self.pos + 2) == Some(&'u')
+                        {
+                            self.advance();
+                            self.advance();
+                            let lo = self.read_hex4()?;
+                            if (0xDC00..=0xDFFF).contains(&lo) {
+                                let cp = 0x10000 + (hi - 0xD800) * 0x400 + (lo - 0xDC00);
+                                if let Some(c) = char::from_u32(cp) { s.push(c); }
+                            } else {
+                                s.push('\u{FFFD}');
+                                if let Some(c) = char::from_u32(lo) { s.push(c); } else { s.push('\u{FFFD}'); }
+                            }
+                        } else if let Some(c) = char::from_u32(hi) {
+                            s.push(c);
+                        }
                     }
                     c => s.push(c),
                 }
             } else { s.push(ch); }
-            self.pos += 1;
+            self.advance();
         }
-        panic!("runtime error: invalid JSON: unterminated string");
+        Err(self.err("invalid JSON: unterminated string".to_string()))
     }
 
     fn lex_number(&mut self) -> JsonToken {
         let start = self.pos;
-        if self.chars[self.pos] == '-' { self.pos += 1; }
-        while self.pos < self.chars.len() && self.chars[self.pos].is_ascii_digit() { self.pos += 1; }
+        if self.chars[self.pos] == '-' { self.advance(); }
+        while self.pos < self.chars.len() && self.chars[self.pos].is_ascii_digit() { self.advance(); }
         if self.pos < self.chars.len() && self.chars[self.pos] == '.' {
-            self.pos += 1;
-            while self.pos < self.chars.len() && self.chars[self.pos].is_ascii_digit() { self.pos += 1; }
+            self.advance();
+            while self.pos < self.chars.len() && self.chars[self.pos].is_ascii_digit() { self.advance(); }
         }
         if self.pos < self.chars.len() && (self.chars[self.pos] == 'e' || self.chars[self.pos] == 'E') {
-            self.pos += 1;
-            if self.pos < self.chars.len() && (self.chars[self.pos] == '+' || self.chars[self.pos] == '-') { self.pos += 1; }
-            while self.pos < self.chars.len() && self.chars[self.pos].is_ascii_digit() { self.pos += 1; }
+            self.advance();
+            if self.pos < self.chars.len() && (self.chars[self.pos] == '+' || self.chars[self.pos] == '-') { self.advance(); }
+            while self.pos < self.chars.len() && self.chars[self.pos].is_ascii_digit() { self.advance(); }
         }
         JsonToken::NumberVal(self.chars[start..self.pos].iter().collect())
     }
 
-    fn lex_kw(&mut self, kw: &str, tok: JsonToken) -> Option<JsonToken> {
+    fn lex_kw(&mut self, kw: &str, tok: JsonToken) -> Result<JsonToken, JsonError> {
         let kc: Vec<char> = kw.chars().collect();
         for (i, &c) in kc.iter().enumerate() {
             if self.pos + i >= self.chars.len() || self.chars[self.pos + i] != c {
-                panic!("runtime error: invalid JSON: unexpected token");
+                return Err(self.err("invalid JSON: unexpected token".to_string()));
             }
         }
-        self.pos += kc.len();
-        Some(tok)
+        for _ in 0..kc.len() { self.advance(); }
+        Ok(tok)
     }
 }
 
-struct JsonParser { tokens: Vec<JsonToken>, pos: usize }
+struct JsonParser { tokens: Vec<(JsonToken, usize, usize)>, pos: usize, end_line: usize, end_column: usize, allow_nan: bool }
 
 impl JsonParser {
-    fn new(input: &str) -> Self {
+    fn new(input: &str, allow_nan: bool) -> Result<Self, JsonError> {
         let mut lex = JsonLexer::new(input);
         let mut tokens = Vec::new();
-        while let Some(t) = lex.next_token() { tokens.push(t); }
-        JsonParser { tokens, pos: 0 }
+        while let Some(t) = lex.next_token()? { tokens.push(t); }
+        Ok(JsonParser { tokens, pos: 0, end_line: lex.line, end_column: lex.column, allow_nan })
+    }
+
+    fn peek_pos(&self) -> (usize, usize) {
+        self.tokens.get(self.pos).map(|(_, l, c)| (*l, *c)).unwrap_or((self.end_line, self.end_column))
+    }
+
+    fn err(&self, message: String) -> JsonError {
+        let (line, column) = self.peek_pos();
+        JsonError { message, line, column }
+    }
+
+    fn peek(&self) -> Option<&JsonToken> { self.tokens.get(self.pos).map(|(t, _, _)| t) }
+
+    fn next(&mut self) -> Result<JsonToken, JsonError> {
+        if self.pos >= self.tokens.len() { return Err(self.err("invalid JSON: unexpected end".to_string())); }
+        let t = self.tokens[self.pos].0.clone();
+        self.pos += 1;
+        Ok(t)
     }
-    fn peek(&self) -> Option<&JsonToken> { self.tokens.get(self.pos) }
-    fn next(&mut self) -> JsonToken {
-        let t = self.tokens.get(self.pos).cloned().unwrap_or_else(|| panic!("runtime error: invalid JSON: unexpected end"));
-        self.pos += 1; t
+
+    fn expect(&mut self, e: &JsonToken) -> Result<(), JsonError> {
+        let (line, column) = self.peek_pos();
+        let t = self.next()?;
+        if &t != e { return Err(JsonError { message: format!("invalid JSON: expected {:?}", e), line, column }); }
+        Ok(())
     }
-    fn expect(&mut self, e: &JsonToken) { let t = self.next(); if &t != e { panic!("runtime error: invalid JSON: expected {:?}", e); } }
 
-    fn parse_value(&mut self) -> Value {
+    fn parse_value(&mut self) -> Result<Value, JsonError> {
         match self.peek().cloned() {
             Some(JsonToken::LBrace) => self.parse_object(),
             Some(JsonToken::LBracket) => self.parse_array(),
-            Some(JsonToken::StringVal(_)) => { if let JsonToken::StringVal(s) = self.next() { Value::Str(s) } else { unreachable!() } }
+            Some(JsonToken::StringVal(_)) => { if let JsonToken::StringVal(s) = self.next()? { Ok(Value::Str(s)) } else { unreachable!() } }
             Some(JsonToken::NumberVal(_)) => {
-                if let JsonToken::NumberVal(s) = self.next() {
+                let (line, column) = self.peek_pos();
+                let bad = || JsonError { message: "invalid JSON number".to_string(), line, column };
+                if let JsonToken::NumberVal(s) = self.next()? {
                     if s.contains('.') || s.contains('e') || s.contains('E') {
-                        Value::Float(s.parse().unwrap_or_else(|_| panic!("runtime error: invalid JSON number")))
+                        s.parse().map(Value::Float).map_err(|_| bad())
                     } else {
-                        Value::Int(s.parse().unwrap_or_else(|_| panic!("runtime error: invalid JSON number")))
+                        match s.parse::<i64>() {
+                            Ok(n) => Ok(Value::Int(n)),
+                            // Integer literal overflows i64 — fall back to a float rather
+                            // than panicking, preserving magnitude at the cost of precision.
+                            Err(_) => s.parse().map(Value::Float).map_err(|_| bad()),
+                        }
                     }
                 } else { unreachable!() }
             }
-            Some(JsonToken::True) => { self.next(); Value::Bool(true) }
-            Some(JsonToken::False) => { self.next(); Value::Bool(false) }
-            Some(JsonToken::Null) => { self.next(); Value::None }
-            _ => panic!("runtime error: invalid JSON: unexpected token"),
+            Some(JsonToken::True) => { self.next()?; Ok(Value::Bool(true)) }
+            Some(JsonToken::False) => { self.next()?; Ok(Value::Bool(false)) }
+            Some(JsonToken::Null) => { self.next()?; Ok(Value::None) }
+            Some(JsonToken::NaN) => {
+                if !self.allow_nan { return Err(self.err("invalid JSON: NaN is not allowed".to_string())); }
+                self.next()?;
+                Ok(Value::Float(f64::NAN))
+            }
+            Some(JsonToken::Infinity) => {
+                if !self.allow_nan { return Err(self.err("invalid JSON: Infinity is not allowed".to_string())); }
+                self.next()?;
+                Ok(Value::Float(f64::INFINITY))
+            }
+            Some(JsonToken::NegInfinity) => {
+                if !self.allow_nan { return Err(self.err("invalid JSON: -Infinity is not allowed".to_string())); }
+                self.next()?;
+                Ok(Value::Float(f64::NEG_INFINITY))
+            }
+            _ => Err(self.err("invalid JSON: unexpected token".to_string())),
         }
     }
 
-    fn parse_object(&mut self) -> Value {
-        self.expect(&JsonToken::LBrace);
+    fn parse_object(&mut self) -> Result<Value, JsonError> {
+        self.expect(&JsonToken::LBrace)?;
         let mut map = OrderedMap::new();
-        if self.peek() == Some(&JsonToken::RBrace) { self.next(); return Value::Map(Rc::new(RefCell::new(map))); }
+        if self.peek() == Some(&JsonToken::RBrace) { self.next()?; return Ok(Value::Map(Arc::new(Mutex::new(map)))); }
         loop {
-            let key = match self.next() { JsonToken::StringVal(s) => s, _ => panic!("runtime error: invalid JSON: expected string key") };
-            self.expect(&JsonToken::Colon);
-            let val = self.parse_value();
+            let (line, column) = self.peek_pos();
+            let key = match self.next()? { JsonToken::StringVal(s) => s, _ => return Err(JsonError { message: "invalid JSON: expected string key".to_string(), line, column }) };
+            self.expect(&JsonToken::Colon)?;
+            let val = self.parse_value()?;
             map.set(Value::Str(key), val);
             match self.peek() {
-                Some(JsonToken::Comma) => { self.next(); }
-                Some(JsonToken::RBrace) => { self.next(); break; }
-                _ => panic!("runtime error: invalid JSON: expected ',' or '}}'"),
+                Some(JsonToken::Comma) => { self.next()?; }
+                Some(JsonToken::RBrace) => { self.next()?; break; }
+                _ => return Err(self.err("invalid JSON: expected ',' or '}'".to_string())),
             }
         }
-        Value::Map(Rc::new(RefCell::new(map)))
+        Ok(Value::Map(Arc::new(Mutex::new(map))))
     }
 
-    fn parse_array(&mut self) -> Value {
-        self.expect(&JsonToken::LBracket);
+    fn parse_array(&mut self) -> Result<Value, JsonError> {
+        self.expect(&JsonToken::LBracket)?;
         let mut items = Vec::new();
-        if self.peek() == Some(&JsonToken::RBracket) { self.next(); return make_array(items); }
+        if self.peek() == Some(&JsonToken::RBracket) { self.next()?; return Ok(make_array(items)); }
         loop {
-            items.push(self.parse_value());
+            items.push(self.parse_value()?);
             match self.peek() {
-                Some(JsonToken::Comma) => { self.next(); }
-                Some(JsonToken::RBracket) => { self.next(); break; }
-                _ => panic!("runtime error: invalid JSON: expected ',' or ']'"),
+                Some(JsonToken::Comma) => { self.next()?; }
+                Some(JsonToken::RBracket) => { self.next()?; break; }
+                _ => return Err(self.err("invalid JSON: expected ',' or ']'".to_string())),
             }
         }
-        make_array(items)
+        Ok(make_array(items))
+    }
+}
+
+fn json_parse_result(input: &str) -> Result<Value, JsonError> {
+    json_parse_result_opt(input, false)
+}
+
+/// Like `json_parse_result`, but lets the caller opt into accepting the
+/// non-standard `NaN`/`Infinity`/`-Infinity` literals (mirrors Python's
+/// `json.loads(..., allow_nan=...)`).
+fn json_parse_result_opt(input: &str, allow_nan: bool) -> Result<Value, JsonError> {
+    let mut parser = JsonParser::new(input, allow_nan)?;
+    let value = parser.parse_value()?;
+    if parser.pos != parser.tokens.len() {
+        return Err(parser.err("invalid JSON: trailing data after value".to_string()));
     }
+    Ok(value)
 }
 
 fn json_parse_val(s: &Value) -> Value {
     let input = match s { Value::Str(st) => st.clone(), _ => panic!("runtime error: JsonParse source must be a string") };
-    let mut parser = JsonParser::new(&input);
-    parser.parse_value()
+    match json_parse_result(&input) {
+        Ok(v) => v,
+        Err(e) => panic!("runtime error: {} (line {}, column {})", e.message, e.line, e.column),
+    }
+}
+
+/// `json_try_parse(s)` builtin: like `json_parse_val`, but never panics on
+/// malformed input — returns a `Value::Record` with `ok: true, value: ...`
+/// on success, or `ok: false, message, line, column` on failure.
+fn json_try_parse(s: &Value) -> Value {
+    let input = match s { Value::Str(st) => st.clone(), _ => panic!("runtime error: json_try_parse source must be a string") };
+    match json_parse_result(&input) {
+        Ok(v) => make_record(vec![("ok", Value::Bool(true)), ("value", v)]),
+        Err(e) => make_record(vec![
+            ("ok", Value::Bool(false)),
+            ("message", Value::Str(e.message)),
+            ("line", Value::Int(e.line as i64)),
+            ("column", Value::Int(e.column as i64)),
+        ]),
+    }
 }
 
 fn json_serialize(v: &Value, indent: Option<usize>, depth: usize) -> String {
@@ -1430,8 +2228,16 @@ fn json_serialize(v: &Value, indent: Option<usize>, depth: usize) -> String {
         Value::None => "null".to_string(),
         Value::Bool(b) => if *b { "true".to_string() } else { "false".to_string() },
         Value::Int(n) => format!("{}", n),
+        Value::BigInt(b) => bigint_to_string(b),
         Value::Float(f) => {
-            if *f == f.floor() && f.abs() < 1e15 { format!("{}.0", *f as i64) } else { format!("{}", f) }
+            // Standard JSON has no NaN/Infinity literals; serialize them as null.
+            if f.is_nan() || f.is_infinite() {
+                "null".to_string()
+            } else if *f == f.floor() && f.abs() < 1e15 {
+                format!("{}.0", *f as i64)
+            } else {
+                format!("{}", f)
+            }
         }
         Value::Str(s) => {
             let mut r = String::from('"');
@@ -1445,77 +2251,817 @@ fn json_serialize(v: &Value, indent: Option<usize>, depth: usize) -> String {
             }
             r.push('"'); r
         }
-        Value::Array(arr) => {
-            let arr = arr.borrow();
-            if arr.is_empty() { return "[]".to_string(); }
-            match indent {
-                Some(ind) => {
-                    let inner = " ".repeat(ind * (depth + 1));
-                    let outer = " ".repeat(ind * depth);
-                    let parts: Vec<String> = arr.iter().map(|i| format!("{}{}", inner, json_serialize(i, Some(ind), depth + 1))).collect();
-                    format!("[\n{}\n{}]", parts.join(",\n"), outer)
+        Value::Array(arr) => json_serialize_seq(arr.lock().unwrap().iter(), indent, depth),
+        Value::Tuple(items) => json_serialize_seq(items.iter(), indent, depth),
+        Value::Map(map) => json_serialize_entries(map.lock().unwrap().entries.iter(), indent, depth),
+        Value::Record(rec) => json_serialize_entries(rec.lock().unwrap().entries.iter(), indent, depth),
+        _ => panic!("TypeError: Object of type {} is not JSON serializable", type_name(v)),
+    }
+}
+
+fn json_serialize_seq<'a>(items: impl Iterator<Item = &'a Value>, indent: Option<usize>, depth: usize) -> String {
+    let items: Vec<&Value> = items.collect();
+    if items.is_empty() { return "[]".to_string(); }
+    match indent {
+        Some(ind) => {
+            let inner = " ".repeat(ind * (depth + 1));
+            let outer = " ".repeat(ind * depth);
+            let parts: Vec<String> = items.iter().map(|i| format!("{}{}", inner, json_serialize(i, Some(ind), depth + 1))).collect();
+            format!("[\n{}\n{}]", parts.join(",\n"), outer)
+        }
+        None => {
+            let parts: Vec<String> = items.iter().map(|i| json_serialize(i, None, depth)).collect();
+            format!("[{}]", parts.join(", "))
+        }
+    }
+}
+
+/// Dict/record keys must be strings in JSON, matching Python's
+/// `TypeError: keys must be str` on `json.dumps({1: "x"})`.
+fn json_serialize_key(k: &Value) -> String {
+    match k {
+        Value::Str(s) => s.clone(),
+        _ => panic!("TypeError: keys must be str, not {}", type_name(k)),
+    }
+}
+
+fn json_serialize_entries<'a>(entries: impl Iterator<Item = &'a (Value, Value)>, indent: Option<usize>, depth: usize) -> String {
+    let entries: Vec<&(Value, Value)> = entries.collect();
+    if entries.is_empty() { return "{}".to_string(); }
+    match indent {
+        Some(ind) => {
+            let inner = " ".repeat(ind * (depth + 1));
+            let outer = " ".repeat(ind * depth);
+            let parts: Vec<String> = entries.iter().map(|(k, v)| {
+                format!("{}{}: {}", inner, json_serialize(&Value::Str(json_serialize_key(k)), Some(ind), depth + 1), json_serialize(v, Some(ind), depth + 1))
+            }).collect();
+            format!("{{\n{}\n{}}}", parts.join(",\n"), outer)
+        }
+        None => {
+            let parts: Vec<String> = entries.iter().map(|(k, v)| {
+                format!("{}: {}", json_serialize(&Value::Str(json_serialize_key(k)), None, depth), json_serialize(v, None, depth))
+            }).collect();
+            format!("{{{}}}", parts.join(", "))
+        }
+    }
+}
+
+fn json_stringify_val(v: &Value, pretty: &Value) -> Value {
+    let indent = if is_truthy(pretty) { Some(2) } else { None };
+    Value::Str(json_serialize(v, indent, 0))
+}
+
+// ============================================================================
+// JSON dumps/loads — Python `json` module compatible codec over Value
+//
+// Both builtins delegate to the JsonLexer/JsonParser/json_serialize stack
+// shared with json_parse_val/json_stringify_val, instead of maintaining a
+// second lexer/decoder pair.
+// ============================================================================
+
+/// `json.dumps`-equivalent builtin: `indent` is `Value::None` for compact
+/// output or a `Value::Int` for pretty-printed output at that width.
+/// Non-positive indents are treated as compact: unlike Python's
+/// `json.dumps(x, indent=0)`, no newlines are inserted.
+fn coreil_json_dumps(v: &Value, indent: &Value) -> Value {
+    let indent = match indent {
+        Value::None => None,
+        Value::Int(n) if *n > 0 => Some(*n as usize),
+        Value::Int(_) => None,
+        _ => panic!("json_dumps: indent must be an int or None"),
+    };
+    Value::Str(json_serialize(v, indent, 0))
+}
+
+/// `json.loads`-equivalent builtin over a `Value::Str` source. `allow_nan`
+/// mirrors Python's `json.loads(..., allow_nan=...)`, except the default
+/// here is to reject `NaN`/`Infinity`/`-Infinity` unless explicitly allowed.
+fn coreil_json_loads(s: &Value, allow_nan: &Value) -> Value {
+    let input = match s { Value::Str(st) => st.clone(), _ => panic!("json_loads: source must be a string") };
+    let allow_nan = match allow_nan {
+        Value::None => false,
+        Value::Bool(b) => *b,
+        _ => panic!("json_loads: allow_nan must be a bool or None"),
+    };
+    match json_parse_result_opt(&input, allow_nan) {
+        Ok(v) => v,
+        Err(e) => panic!("runtime error: {} (line {}, column {})", e.message, e.line, e.column),
+    }
+}
+
+// ============================================================================
+// JSONPath — query engine over parsed Value trees
+// ============================================================================
+
+#[derive(Clone, Debug)]
+enum JsonPathSeg {
+    Child(String),
+    RecursiveDescent(String),
+    Index(i64),
+    Wildcard,
+    Slice(Option<i64>, Option<i64>),
+    Filter(String, String, Value),
+}
+
+fn jsonpath_read_name(chars: &[char], pos: &mut usize) -> String {
+    let start = *pos;
+    while *pos < chars.len() && (chars[*pos].is_alphanumeric() || chars[*pos] == '_') {
+        *pos += 1;
+    }
+    chars[start..*pos].iter().collect()
+}
+
+fn jsonpath_parse_literal(chars: &[char], pos: &mut usize) -> Value {
+    while *pos < chars.len() && chars[*pos] == ' ' { *pos += 1; }
+    if chars.get(*pos) == Some(&'\'') || chars.get(*pos) == Some(&'"') {
+        let quote = chars[*pos];
+        *pos += 1;
+        let start = *pos;
+        while *pos < chars.len() && chars[*pos] != quote { *pos += 1; }
+        let s: String = chars[start..*pos].iter().collect();
+        *pos += 1; // closing quote
+        Value::Str(s)
+    } else if chars[*pos..].starts_with(&['t', 'r', 'u', 'e']) {
+        *pos += 4;
+        Value::Bool(true)
+    } else if chars[*pos..].starts_with(&['f', 'a', 'l', 's', 'e']) {
+        *pos += 5;
+        Value::Bool(false)
+    } else {
+        let start = *pos;
+        if chars.get(*pos) == Some(&'-') { *pos += 1; }
+        let mut is_float = false;
+        while *pos < chars.len() && (chars[*pos].is_ascii_digit() || chars[*pos] == '.') {
+            if chars[*pos] == '.' { is_float = true; }
+            *pos += 1;
+        }
+        let text: String = chars[start..*pos].iter().collect();
+        if is_float {
+            Value::Float(text.parse().unwrap_or(0.0))
+        } else {
+            Value::Int(text.parse().unwrap_or(0))
+        }
+    }
+}
+
+fn jsonpath_tokenize(path: &str) -> Vec<JsonPathSeg> {
+    let chars: Vec<char> = path.chars().collect();
+    let mut pos = 0;
+    if chars.first() == Some(&'$') {
+        pos = 1;
+    }
+    let mut segs = Vec::new();
+    while pos < chars.len() {
+        match chars[pos] {
+            '.' => {
+                pos += 1;
+                if chars.get(pos) == Some(&'.') {
+                    pos += 1;
+                    segs.push(JsonPathSeg::RecursiveDescent(jsonpath_read_name(&chars, &mut pos)));
+                } else {
+                    segs.push(JsonPathSeg::Child(jsonpath_read_name(&chars, &mut pos)));
                 }
-                None => {
-                    let parts: Vec<String> = arr.iter().map(|i| json_serialize(i, None, depth)).collect();
-                    format!("[{}]", parts.join(", "))
+            }
+            '[' => {
+                pos += 1;
+                if chars.get(pos) == Some(&'*') {
+                    pos += 1;
+                    segs.push(JsonPathSeg::Wildcard);
+                } else if chars.get(pos) == Some(&'?') {
+                    pos += 1; // '?'
+                    pos += 1; // '('
+                    pos += 1; // '@'
+                    pos += 1; // '.'
+                    let field = jsonpath_read_name(&chars, &mut pos);
+                    while chars.get(pos) == Some(&' ') { pos += 1; }
+                    let op_start = pos;
+                    while pos < chars.len() && "=!<>".contains(chars[pos]) { pos += 1; }
+                    let op: String = chars[op_start..pos].iter().collect();
+                    let literal = jsonpath_parse_literal(&chars, &mut pos);
+                    while chars.get(pos) == Some(&' ') { pos += 1; }
+                    if chars.get(pos) == Some(&')') { pos += 1; }
+                    segs.push(JsonPathSeg::Filter(field, op, literal));
+                } else if chars.get(pos) == Some(&'\'') || chars.get(pos) == Some(&'"') {
+                    let quote = chars[pos];
+                    pos += 1;
+                    let start = pos;
+                    while pos < chars.len() && chars[pos] != quote { pos += 1; }
+                    segs.push(JsonPathSeg::Child(chars[start..pos].iter().collect()));
+                    pos += 1;
+                } else {
+                    let start = pos;
+                    while pos < chars.len() && chars[pos] != ']' && chars[pos] != ':' { pos += 1; }
+                    let first: String = chars[start..pos].iter().collect();
+                    if chars.get(pos) == Some(&':') {
+                        pos += 1;
+                        let end_start = pos;
+                        while pos < chars.len() && chars[pos] != ']' { pos += 1; }
+                        let second: String = chars[end_start..pos].iter().collect();
+                        segs.push(JsonPathSeg::Slice(first.parse().ok(), second.parse().ok()));
+                    } else {
+                        segs.push(JsonPathSeg::Index(first.parse().unwrap_or(0)));
+                    }
                 }
+                if chars.get(pos) == Some(&']') { pos += 1; }
+            }
+            _ => panic!("runtime error: invalid JSONPath expression"),
+        }
+    }
+    segs
+}
+
+fn jsonpath_get_child(n: &Value, name: &str) -> Option<Value> {
+    match n {
+        Value::Map(m) => m.lock().unwrap().get(&Value::Str(name.to_string())).cloned(),
+        Value::Record(r) => r.lock().unwrap().get(&Value::Str(name.to_string())).cloned(),
+        _ => None,
+    }
+}
+
+fn jsonpath_children(n: &Value) -> Vec<Value> {
+    match n {
+        Value::Map(m) => m.lock().unwrap().entries.iter().map(|(_, v)| v.clone()).collect(),
+        Value::Record(r) => r.lock().unwrap().entries.iter().map(|(_, v)| v.clone()).collect(),
+        Value::Array(a) => a.lock().unwrap().clone(),
+        _ => Vec::new(),
+    }
+}
+
+fn jsonpath_index(n: &Value, idx: i64) -> Option<Value> {
+    match n {
+        Value::Array(a) => {
+            let a = a.lock().unwrap();
+            let len = a.len() as i64;
+            let resolved = if idx < 0 { idx + len } else { idx };
+            if resolved >= 0 && resolved < len {
+                Some(a[resolved as usize].clone())
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+fn jsonpath_slice(n: &Value, start: Option<i64>, stop: Option<i64>) -> Vec<Value> {
+    match n {
+        Value::Array(a) => {
+            let a = a.lock().unwrap();
+            let len = a.len() as i64;
+            let s = resolve_slice_index(start.unwrap_or(0), len);
+            let e = resolve_slice_index(stop.unwrap_or(len), len);
+            if s >= e { Vec::new() } else { a[s..e].to_vec() }
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn jsonpath_filter_matches(c: &Value, field: &str, op: &str, lit: &Value) -> bool {
+    let fv = match jsonpath_get_child(c, field) {
+        Some(v) => v,
+        None => return false,
+    };
+    match op {
+        "==" => values_equal(&fv, lit),
+        "!=" => !values_equal(&fv, lit),
+        "<" | "<=" | ">" | ">=" => {
+            let ord = compare_values(&fv, lit);
+            match op {
+                "<" => ord == Ordering::Less,
+                "<=" => ord != Ordering::Greater,
+                ">" => ord == Ordering::Greater,
+                _ => ord != Ordering::Less,
+            }
+        }
+        _ => false,
+    }
+}
+
+fn jsonpath_collect_descendants(n: &Value, name: &str, out: &mut Vec<Value>) {
+    if let Some(v) = jsonpath_get_child(n, name) {
+        out.push(v);
+    }
+    match n {
+        Value::Map(m) => {
+            for (_, v) in m.lock().unwrap().entries.iter() {
+                jsonpath_collect_descendants(v, name, out);
+            }
+        }
+        Value::Record(r) => {
+            for (_, v) in r.lock().unwrap().entries.iter() {
+                jsonpath_collect_descendants(v, name, out);
+            }
+        }
+        Value::Array(a) => {
+            for v in a.lock().unwrap().iter() {
+                jsonpath_collect_descendants(v, name, out);
             }
         }
-        Value::Map(map) => {
-            let map = map.borrow();
-            if map.entries.is_empty() { return "{}".to_string(); }
-            match indent {
-                Some(ind) => {
-                    let inner = " ".repeat(ind * (depth + 1));
-                    let outer = " ".repeat(ind * depth);
-                    let parts: Vec<String> = map.entries.iter().map(|(k, v)| {
-                        format!("{}{}: {}", inner, json_serialize(k, Some(ind), depth + 1), json_serialize(v, Some(ind), depth + 1))
-                    }).collect();
-                    format!("{{\n{}\n{}}}", parts.join(",\n"), outer)
+        _ => {}
+    }
+}
+
+fn jsonpath_apply(nodes: &[Value], seg: &JsonPathSeg) -> Vec<Value> {
+    match seg {
+        JsonPathSeg::Child(name) => nodes.iter().filter_map(|n| jsonpath_get_child(n, name)).collect(),
+        JsonPathSeg::Wildcard => nodes.iter().flat_map(jsonpath_children).collect(),
+        JsonPathSeg::Index(i) => nodes.iter().filter_map(|n| jsonpath_index(n, *i)).collect(),
+        JsonPathSeg::Slice(s, e) => nodes.iter().flat_map(|n| jsonpath_slice(n, *s, *e)).collect(),
+        JsonPathSeg::RecursiveDescent(name) => {
+            let mut result = Vec::new();
+            for n in nodes {
+                jsonpath_collect_descendants(n, name, &mut result);
+            }
+            result
+        }
+        JsonPathSeg::Filter(field, op, lit) => nodes
+            .iter()
+            .flat_map(jsonpath_children)
+            .filter(|c| jsonpath_filter_matches(c, field, op, lit))
+            .collect(),
+    }
+}
+
+/// `json_path(value, "$.store.book[*].author")` builtin: evaluates a
+/// JSONPath expression against `value` and returns all matches as an array.
+fn json_path(value: &Value, path: &Value) -> Value {
+    let path = match path { Value::Str(s) => s.clone(), _ => panic!("json_path: path must be a string") };
+    let segs = jsonpath_tokenize(&path);
+    let mut current = vec![value.clone()];
+    for seg in &segs {
+        current = jsonpath_apply(&current, seg);
+    }
+    make_array(current)
+}
+
+// ============================================================================
+// JSON Query — jq-style transform pipeline over Values
+// ============================================================================
+
+#[derive(Clone, Debug)]
+enum JqFilter {
+    Identity,
+    Field(String),
+    Index(i64),
+    Iterate,
+    Pipe(Box<JqFilter>, Box<JqFilter>),
+    ArrayConstruct(Box<JqFilter>),
+    ObjectConstruct(Vec<(String, JqFilter)>),
+    Length,
+    Keys,
+    Select(Box<JqFilter>),
+    Map(Box<JqFilter>),
+}
+
+fn jq_skip_ws(chars: &[char], pos: &mut usize) {
+    while *pos < chars.len() && chars[*pos].is_whitespace() { *pos += 1; }
+}
+
+fn jq_read_ident(chars: &[char], pos: &mut usize) -> String {
+    let start = *pos;
+    while *pos < chars.len() && (chars[*pos].is_alphanumeric() || chars[*pos] == '_') {
+        *pos += 1;
+    }
+    chars[start..*pos].iter().collect()
+}
+
+fn jq_read_string(chars: &[char], pos: &mut usize) -> String {
+    let quote = chars[*pos];
+    *pos += 1;
+    let start = *pos;
+    while *pos < chars.len() && chars[*pos] != quote { *pos += 1; }
+    let s: String = chars[start..*pos].iter().collect();
+    *pos += 1;
+    s
+}
+
+fn jq_parse_suffixes(chars: &[char], pos: &mut usize, mut cur: JqFilter) -> JqFilter {
+    loop {
+        jq_skip_ws(chars, pos);
+        if chars.get(*pos) == Some(&'.') && chars.get(*pos + 1).map_or(false, |c| c.is_alphabetic() || *c == '_') {
+            *pos += 1;
+            let name = jq_read_ident(chars, pos);
+            cur = JqFilter::Pipe(Box::new(cur), Box::new(JqFilter::Field(name)));
+        } else if chars.get(*pos) == Some(&'[') {
+            *pos += 1;
+            jq_skip_ws(chars, pos);
+            if chars.get(*pos) == Some(&'"') || chars.get(*pos) == Some(&'\'') {
+                let name = jq_read_string(chars, pos);
+                jq_skip_ws(chars, pos);
+                if chars.get(*pos) == Some(&']') { *pos += 1; }
+                cur = JqFilter::Pipe(Box::new(cur), Box::new(JqFilter::Field(name)));
+            } else if chars.get(*pos) == Some(&']') {
+                *pos += 1;
+                cur = JqFilter::Pipe(Box::new(cur), Box::new(JqFilter::Iterate));
+            } else {
+                let start = *pos;
+                if chars.get(*pos) == Some(&'-') { *pos += 1; }
+                while *pos < chars.len() && chars[*pos].is_ascii_digit() { *pos += 1; }
+                let n: i64 = chars[start..*pos].iter().collect::<String>().parse().unwrap_or(0);
+                jq_skip_ws(chars, pos);
+                if chars.get(*pos) == Some(&']') { *pos += 1; }
+                cur = JqFilter::Pipe(Box::new(cur), Box::new(JqFilter::Index(n)));
+            }
+        } else {
+            break;
+        }
+    }
+    cur
+}
+
+fn jq_parse_primary(chars: &[char], pos: &mut usize) -> JqFilter {
+    jq_skip_ws(chars, pos);
+    match chars.get(*pos) {
+        Some('.') => {
+            *pos += 1;
+            let base = if chars.get(*pos).map_or(false, |c| c.is_alphabetic() || *c == '_') {
+                JqFilter::Field(jq_read_ident(chars, pos))
+            } else {
+                JqFilter::Identity
+            };
+            jq_parse_suffixes(chars, pos, base)
+        }
+        Some('[') => {
+            *pos += 1;
+            let inner = jq_parse_pipe(chars, pos);
+            jq_skip_ws(chars, pos);
+            if chars.get(*pos) == Some(&']') { *pos += 1; }
+            jq_parse_suffixes(chars, pos, JqFilter::ArrayConstruct(Box::new(inner)))
+        }
+        Some('{') => {
+            *pos += 1;
+            let mut fields = Vec::new();
+            loop {
+                jq_skip_ws(chars, pos);
+                if chars.get(*pos) == Some(&'}') { *pos += 1; break; }
+                let key = if chars.get(*pos) == Some(&'"') || chars.get(*pos) == Some(&'\'') {
+                    jq_read_string(chars, pos)
+                } else {
+                    jq_read_ident(chars, pos)
+                };
+                jq_skip_ws(chars, pos);
+                let value_filter = if chars.get(*pos) == Some(&':') {
+                    *pos += 1;
+                    jq_parse_pipe(chars, pos)
+                } else {
+                    JqFilter::Field(key.clone())
+                };
+                fields.push((key, value_filter));
+                jq_skip_ws(chars, pos);
+                if chars.get(*pos) == Some(&',') { *pos += 1; }
+            }
+            jq_parse_suffixes(chars, pos, JqFilter::ObjectConstruct(fields))
+        }
+        Some(c) if c.is_alphabetic() || *c == '_' => {
+            let name = jq_read_ident(chars, pos);
+            jq_skip_ws(chars, pos);
+            let filter = match name.as_str() {
+                "length" => JqFilter::Length,
+                "keys" => JqFilter::Keys,
+                "select" => {
+                    if chars.get(*pos) == Some(&'(') { *pos += 1; }
+                    let inner = jq_parse_pipe(chars, pos);
+                    jq_skip_ws(chars, pos);
+                    if chars.get(*pos) == Some(&')') { *pos += 1; }
+                    JqFilter::Select(Box::new(inner))
                 }
-                None => {
-                    let parts: Vec<String> = map.entries.iter().map(|(k, v)| {
-                        format!("{}: {}", json_serialize(k, None, depth), json_serialize(v, None, depth))
-                    }).collect();
-                    format!("{{{}}}", parts.join(", "))
+                "map" => {
+                    if chars.get(*pos) == Some(&'(') { *pos += 1; }
+                    let inner = jq_parse_pipe(chars, pos);
+                    jq_skip_ws(chars, pos);
+                    if chars.get(*pos) == Some(&')') { *pos += 1; }
+                    JqFilter::Map(Box::new(inner))
                 }
+                _ => panic!("runtime error: unknown json_query builtin `{}`", name),
+            };
+            jq_parse_suffixes(chars, pos, filter)
+        }
+        _ => panic!("runtime error: invalid json_query filter"),
+    }
+}
+
+fn jq_parse_pipe(chars: &[char], pos: &mut usize) -> JqFilter {
+    let left = jq_parse_primary(chars, pos);
+    jq_skip_ws(chars, pos);
+    if chars.get(*pos) == Some(&'|') {
+        *pos += 1;
+        let right = jq_parse_pipe(chars, pos);
+        JqFilter::Pipe(Box::new(left), Box::new(right))
+    } else {
+        left
+    }
+}
+
+fn jq_parse(filter: &str) -> JqFilter {
+    let chars: Vec<char> = filter.chars().collect();
+    let mut pos = 0;
+    let parsed = jq_parse_pipe(&chars, &mut pos);
+    jq_skip_ws(&chars, &mut pos);
+    if pos != chars.len() {
+        panic!("runtime error: trailing data in json_query filter");
+    }
+    parsed
+}
+
+fn jq_length(v: &Value) -> i64 {
+    match v {
+        Value::Array(a) => a.lock().unwrap().len() as i64,
+        Value::Tuple(t) => t.len() as i64,
+        Value::Map(m) => m.lock().unwrap().entries.len() as i64,
+        Value::Record(r) => r.lock().unwrap().entries.len() as i64,
+        Value::Set(s) => s.lock().unwrap().items.len() as i64,
+        Value::Str(s) => s.chars().count() as i64,
+        Value::None => 0,
+        _ => panic!("runtime error: json_query length: unsupported type {}", type_name(v)),
+    }
+}
+
+fn jq_keys(v: &Value) -> Value {
+    let mut keys: Vec<Value> = match v {
+        Value::Map(m) => m.lock().unwrap().keys(),
+        Value::Record(r) => r.lock().unwrap().keys(),
+        _ => panic!("runtime error: json_query keys: unsupported type {}", type_name(v)),
+    };
+    keys.sort_by(compare_values);
+    make_array(keys)
+}
+
+fn jq_eval(filter: &JqFilter, input: &Value) -> Vec<Value> {
+    match filter {
+        JqFilter::Identity => vec![input.clone()],
+        JqFilter::Field(name) => {
+            let v = jsonpath_get_child(input, name).unwrap_or(Value::None);
+            vec![v]
+        }
+        JqFilter::Index(i) => vec![jsonpath_index(input, *i).unwrap_or(Value::None)],
+        JqFilter::Iterate => jsonpath_children(input),
+        JqFilter::Pipe(a, b) => jq_eval(a, input).iter().flat_map(|v| jq_eval(b, v)).collect(),
+        JqFilter::ArrayConstruct(inner) => vec![make_array(jq_eval(inner, input))],
+        JqFilter::ObjectConstruct(fields) => {
+            let mut map = OrderedMap::new();
+            for (key, f) in fields {
+                let v = jq_eval(f, input).into_iter().next().unwrap_or(Value::None);
+                map.set(Value::Str(key.clone()), v);
             }
+            vec![Value::Map(Arc::new(Mutex::new(map)))]
+        }
+        JqFilter::Length => vec![Value::Int(jq_length(input))],
+        JqFilter::Keys => vec![jq_keys(input)],
+        JqFilter::Select(cond) => {
+            let matched = jq_eval(cond, input).into_iter().next().map_or(false, |v| is_truthy(&v));
+            if matched { vec![input.clone()] } else { Vec::new() }
+        }
+        JqFilter::Map(inner) => {
+            let items = jsonpath_children(input);
+            let mapped: Vec<Value> = items
+                .iter()
+                .map(|item| jq_eval(inner, item).into_iter().next().unwrap_or(Value::None))
+                .collect();
+            vec![make_array(mapped)]
         }
-        _ => json_serialize(&Value::Str(format_value(v)), indent, depth),
     }
 }
 
-fn json_stringify_val(v: &Value, pretty: &Value) -> Value {
-    let indent = if is_truthy(pretty) { Some(2) } else { None };
-    Value::Str(json_serialize(v, indent, 0))
+/// `json_query(value, ".foo[] | select(.active) | .name")` builtin: runs a
+/// compact jq-style filter over `value` and collects the resulting stream
+/// into a `Value::Array`.
+fn json_query(value: &Value, filter_string: &Value) -> Value {
+    let filter_string = match filter_string {
+        Value::Str(s) => s.clone(),
+        _ => panic!("json_query: filter must be a string"),
+    };
+    let filter = jq_parse(&filter_string);
+    make_array(jq_eval(&filter, value))
+}
+
+// ============================================================================
+// JSON Path Mutation — deep set/remove over parsed Value trees
+// ============================================================================
+
+enum JsonPathKey {
+    Str(String),
+    Int(i64),
+}
+
+fn json_path_keys(path: &Value) -> Vec<JsonPathKey> {
+    match path {
+        Value::Array(a) => a
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|v| match v {
+                Value::Str(s) => JsonPathKey::Str(s.clone()),
+                Value::Int(i) => JsonPathKey::Int(*i),
+                _ => panic!("runtime error: json path elements must be strings or integers"),
+            })
+            .collect(),
+        _ => panic!("runtime error: json path must be an array"),
+    }
+}
+
+fn json_path_get_child(container: &Value, key: &JsonPathKey) -> Option<Value> {
+    match (container, key) {
+        (Value::Map(m), JsonPathKey::Str(s)) => m.lock().unwrap().get(&Value::Str(s.clone())).cloned(),
+        (Value::Record(r), JsonPathKey::Str(s)) => r.lock().unwrap().get(&Value::Str(s.clone())).cloned(),
+        (Value::Array(a), JsonPathKey::Int(i)) => {
+            let a = a.lock().unwrap();
+            let len = a.len() as i64;
+            let resolved = if *i < 0 { i + len } else { *i };
+            if resolved >= 0 && resolved < len { Some(a[resolved as usize].clone()) } else { None }
+        }
+        _ => None,
+    }
+}
+
+fn json_path_assign_child(container: &Value, key: &JsonPathKey, value: Value) {
+    match (container, key) {
+        (Value::Map(m), JsonPathKey::Str(s)) => {
+            m.lock().unwrap().set(Value::Str(s.clone()), value);
+        }
+        (Value::Record(r), JsonPathKey::Str(s)) => {
+            r.lock().unwrap().set(Value::Str(s.clone()), value);
+        }
+        (Value::Array(a), JsonPathKey::Int(i)) => {
+            let mut a = a.lock().unwrap();
+            let len = a.len() as i64;
+            let resolved = if *i < 0 { i + len } else { *i };
+            if resolved < 0 {
+                panic!("runtime error: json_set_path: negative index out of range");
+            }
+            let resolved = resolved as usize;
+            while a.len() <= resolved {
+                a.push(Value::None);
+            }
+            a[resolved] = value;
+        }
+        _ => panic!(
+            "runtime error: json_set_path: path element does not match container type {}",
+            type_name(container)
+        ),
+    }
+}
+
+fn json_path_remove_child(container: &Value, key: &JsonPathKey) {
+    match (container, key) {
+        (Value::Map(m), JsonPathKey::Str(s)) => m.lock().unwrap().remove(&Value::Str(s.clone())),
+        (Value::Record(r), JsonPathKey::Str(s)) => r.lock().unwrap().remove(&Value::Str(s.clone())),
+        (Value::Array(a), JsonPathKey::Int(i)) => {
+            let mut a = a.lock().unwrap();
+            let len = a.len() as i64;
+            let resolved = if *i < 0 { i + len } else { *i };
+            if resolved >= 0 && resolved < len {
+                a.remove(resolved as usize);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// `json_set_path(root, ["store", "book", 0, "title"], value)` builtin:
+/// navigates `root` by a path of string keys / integer indices and writes
+/// `value` at the end of it, auto-vivifying missing `Map`/`Array`
+/// containers along the way.
+fn json_set_path(root: &Value, path: &Value, value: &Value) {
+    let keys = json_path_keys(path);
+    if keys.is_empty() {
+        panic!("runtime error: json_set_path: path must not be empty");
+    }
+    let mut current = root.clone();
+    for i in 0..keys.len() - 1 {
+        if let Some(child) = json_path_get_child(&current, &keys[i]) {
+            current = child;
+        } else {
+            let container = match &keys[i + 1] {
+                JsonPathKey::Str(_) => Value::Map(Arc::new(Mutex::new(OrderedMap::new()))),
+                JsonPathKey::Int(_) => Value::Array(Arc::new(Mutex::new(Vec::new()))),
+            };
+            json_path_assign_child(&current, &keys[i], container.clone());
+            current = container;
+        }
+    }
+    json_path_assign_child(&current, &keys[keys.len() - 1], value.clone());
+}
+
+/// `json_remove_path(root, ["store", "book", 0])` builtin: deletes the
+/// value at the end of `path`, silently doing nothing if any segment along
+/// the way does not exist.
+fn json_remove_path(root: &Value, path: &Value) {
+    let keys = json_path_keys(path);
+    if keys.is_empty() {
+        return;
+    }
+    let mut current = root.clone();
+    for i in 0..keys.len() - 1 {
+        match json_path_get_child(&current, &keys[i]) {
+            Some(child) => current = child,
+            None => return,
+        }
+    }
+    json_path_remove_child(&current, &keys[keys.len() - 1]);
 }
 
 // ============================================================================
 // Regex Operations — Pure Rust NFA-based regex engine
 // ============================================================================
 
+/// Unicode general-category groupings recognized by `\p{...}`/`\P{...}`,
+/// classified via `char`'s own predicates since no Unicode-data table is
+/// available in this std-only runtime.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum UnicodeCategory {
+    Letter,
+    UppercaseLetter,
+    LowercaseLetter,
+    Number,
+    Whitespace,
+    Punctuation,
+}
+
+fn rx_category_matches(cat: UnicodeCategory, c: char) -> bool {
+    match cat {
+        UnicodeCategory::Letter => c.is_alphabetic(),
+        UnicodeCategory::UppercaseLetter => c.is_uppercase(),
+        UnicodeCategory::LowercaseLetter => c.is_lowercase(),
+        UnicodeCategory::Number => c.is_numeric(),
+        UnicodeCategory::Whitespace => c.is_whitespace(),
+        UnicodeCategory::Punctuation => !c.is_alphanumeric() && !c.is_whitespace() && !c.is_control(),
+    }
+}
+
+/// Parses a `{Name}` category tag immediately following `\p`/`\P` (`chars[*pos]`
+/// must be the `{`). Returns `None` (consuming nothing) for an unrecognized
+/// or malformed tag, leaving the caller to fall back to a literal `p`/`P`.
+fn rx_parse_category_name(chars: &[char], pos: &mut usize) -> Option<UnicodeCategory> {
+    if *pos >= chars.len() || chars[*pos] != '{' { return None; }
+    let start = *pos + 1;
+    let mut end = start;
+    while end < chars.len() && chars[end] != '}' { end += 1; }
+    if end >= chars.len() { return None; }
+    let name: String = chars[start..end].iter().collect();
+    let cat = match name.as_str() {
+        "L" => UnicodeCategory::Letter,
+        "Lu" => UnicodeCategory::UppercaseLetter,
+        "Ll" => UnicodeCategory::LowercaseLetter,
+        "N" => UnicodeCategory::Number,
+        "Z" | "Zs" => UnicodeCategory::Whitespace,
+        "P" => UnicodeCategory::Punctuation,
+        _ => return None,
+    };
+    *pos = end + 1;
+    Some(cat)
+}
+
+/// Returns the case-fold set for `c`: itself plus the results of
+/// `to_lowercase()`/`to_uppercase()` that are a single char. A char can
+/// therefore have several variants (e.g. Greek final sigma `ς` also gets
+/// `Σ`), but a multi-char expansion such as `İ` -> "i̇" adds nothing.
+fn rx_case_variants(c: char) -> Vec<char> {
+    let mut variants = vec![c];
+    // Only single-character mappings count as "simple" case folding (e.g. 'ß'
+    // expands to "SS" under full uppercasing, which would wrongly make it
+    // equivalent to plain 'S' here) — multi-char expansions are skipped.
+    let lower: Vec<char> = c.to_lowercase().collect();
+    if lower.len() == 1 && !variants.contains(&lower[0]) { variants.push(lower[0]); }
+    let upper: Vec<char> = c.to_uppercase().collect();
+    if upper.len() == 1 && !variants.contains(&upper[0]) { variants.push(upper[0]); }
+    variants
+}
+
 #[derive(Clone, Debug)]
 enum RxInst {
     Lit(char),
-    LitCI(char, char),
+    LitCI(Vec<char>),
     Dot,
     AnchorStart,
     AnchorEnd,
-    Class(Vec<(char, char)>, bool),
+    Class(Vec<(char, char)>, Vec<UnicodeCategory>, bool),
+    UnicodeClass(UnicodeCategory, bool),
     Split(usize, usize),
     Jump(usize),
+    // Records the current position into capture slot `n` (slot 0/1 bracket
+    // the whole match, slot 2k/2k+1 bracket group k) and falls through.
+    Save(usize),
+    // Zero-width assertion: `true` for `\b` (a word boundary must exist),
+    // `false` for `\B` (a word boundary must not exist).
+    WordBoundary(bool),
     Match,
 }
 
-fn rx_compile(pattern: &str, ci: bool) -> Vec<RxInst> {
+/// Compiles `pattern` to NFA instructions plus the number of capturing
+/// groups found (group 0 is the whole match; slots are `2 * (groups + 1)`).
+fn rx_compile(pattern: &str, ci: bool) -> (Vec<RxInst>, usize) {
     let chars: Vec<char> = pattern.chars().collect();
-    let mut insts = Vec::new();
-    rx_compile_inner(&chars, &mut 0, &mut insts, ci, false);
+    let mut body = Vec::new();
+    let mut group_count = 0usize;
+    rx_compile_inner(&chars, &mut 0, &mut body, ci, false, &mut group_count);
+    let mut insts = vec![RxInst::Save(0)];
+    insts.extend(rx_rebase(&body, 1));
+    insts.push(RxInst::Save(1));
     insts.push(RxInst::Match);
-    insts
+    (insts, group_count)
 }
 
-fn rx_compile_inner(chars: &[char], pos: &mut usize, insts: &mut Vec<RxInst>, ci: bool, in_group: bool) {
+fn rx_compile_inner(chars: &[char], pos: &mut usize, insts: &mut Vec<RxInst>, ci: bool, in_group: bool, group_count: &mut usize) {
     let mut alts: Vec<Vec<RxInst>> = vec![Vec::new()];
 
     while *pos < chars.len() {
@@ -1525,15 +3071,20 @@ fn rx_compile_inner(chars: &[char], pos: &mut usize, insts: &mut Vec<RxInst>, ci
             '|' => { *pos += 1; alts.push(Vec::new()); }
             '(' => {
                 *pos += 1;
-                let mut sub = Vec::new();
-                rx_compile_inner(chars, pos, &mut sub, ci, true);
+                *group_count += 1;
+                let gid = *group_count;
+                let mut body = Vec::new();
+                rx_compile_inner(chars, pos, &mut body, ci, true, group_count);
                 if *pos < chars.len() && chars[*pos] == ')' { *pos += 1; }
+                let mut sub = vec![RxInst::Save(gid * 2)];
+                sub.extend(rx_rebase(&body, 1));
+                sub.push(RxInst::Save(gid * 2 + 1));
                 rx_apply_quant(chars, pos, &mut sub, alts.last_mut().unwrap());
             }
             '[' => {
                 *pos += 1;
-                let (ranges, neg) = rx_parse_class(chars, pos);
-                let mut sub = vec![RxInst::Class(ranges, neg)];
+                let (ranges, cats, neg) = rx_parse_class(chars, pos);
+                let mut sub = vec![RxInst::Class(ranges, cats, neg)];
                 rx_apply_quant(chars, pos, &mut sub, alts.last_mut().unwrap());
             }
             '.' => { *pos += 1; let mut sub = vec![RxInst::Dot]; rx_apply_quant(chars, pos, &mut sub, alts.last_mut().unwrap()); }
@@ -1544,14 +3095,20 @@ fn rx_compile_inner(chars: &[char], pos: &mut usize, insts: &mut Vec<RxInst>, ci
                 if *pos >= chars.len() { panic!("runtime error: invalid regex: trailing backslash"); }
                 let esc = chars[*pos]; *pos += 1;
                 let mut sub = match esc {
-                    'd' => vec![RxInst::Class(vec![('0', '9')], false)],
-                    'D' => vec![RxInst::Class(vec![('0', '9')], true)],
-                    'w' => vec![RxInst::Class(vec![('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')], false)],
-                    'W' => vec![RxInst::Class(vec![('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')], true)],
-                    's' => vec![RxInst::Class(vec![(' ', ' '), ('\t', '\t'), ('\n', '\n'), ('\r', '\r')], false)],
-                    'S' => vec![RxInst::Class(vec![(' ', ' '), ('\t', '\t'), ('\n', '\n'), ('\r', '\r')], true)],
+                    'd' => vec![RxInst::Class(vec![('0', '9')], vec![], false)],
+                    'D' => vec![RxInst::Class(vec![('0', '9')], vec![], true)],
+                    'w' => vec![RxInst::Class(vec![('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')], vec![], false)],
+                    'W' => vec![RxInst::Class(vec![('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')], vec![], true)],
+                    's' => vec![RxInst::Class(vec![(' ', ' '), ('\t', '\t'), ('\n', '\n'), ('\r', '\r')], vec![], false)],
+                    'S' => vec![RxInst::Class(vec![(' ', ' '), ('\t', '\t'), ('\n', '\n'), ('\r', '\r')], vec![], true)],
+                    'b' => vec![RxInst::WordBoundary(true)],
+                    'B' => vec![RxInst::WordBoundary(false)],
+                    'p' | 'P' => match rx_parse_category_name(chars, pos) {
+                        Some(cat) => vec![RxInst::UnicodeClass(cat, esc == 'P')],
+                        None => if ci && esc.is_alphabetic() { vec![RxInst::LitCI(rx_case_variants(esc))] } else { vec![RxInst::Lit(esc)] },
+                    },
                     c => if ci && c.is_alphabetic() {
-                        vec![RxInst::LitCI(c.to_lowercase().next().unwrap(), c.to_uppercase().next().unwrap())]
+                        vec![RxInst::LitCI(rx_case_variants(c))]
                     } else { vec![RxInst::Lit(c)] },
                 };
                 rx_apply_quant(chars, pos, &mut sub, alts.last_mut().unwrap());
@@ -1559,7 +3116,7 @@ fn rx_compile_inner(chars: &[char], pos: &mut usize, insts: &mut Vec<RxInst>, ci
             _ => {
                 *pos += 1;
                 let mut sub = if ci && ch.is_alphabetic() {
-                    vec![RxInst::LitCI(ch.to_lowercase().next().unwrap(), ch.to_uppercase().next().unwrap())]
+                    vec![RxInst::LitCI(rx_case_variants(ch))]
                 } else { vec![RxInst::Lit(ch)] };
                 rx_apply_quant(chars, pos, &mut sub, alts.last_mut().unwrap());
             }
@@ -1605,7 +3162,7 @@ fn rx_apply_quant(chars: &[char], pos: &mut usize, sub: &mut Vec<RxInst>, target
                 let greedy = !(*pos < chars.len() && chars[*pos] == '?');
                 if !greedy { *pos += 1; }
                 let start = target.len();
-                target.extend(sub.drain(..));
+                target.extend(rx_rebase(sub, start));
                 if greedy { target.push(RxInst::Split(start, target.len() + 1)); }
                 else { target.push(RxInst::Split(target.len() + 1, start)); }
                 return;
@@ -1617,20 +3174,106 @@ fn rx_apply_quant(chars: &[char], pos: &mut usize, sub: &mut Vec<RxInst>, target
                 let spos = target.len();
                 target.push(RxInst::Split(0, 0)); // placeholder
                 let bstart = target.len();
-                target.extend(sub.drain(..));
+                target.extend(rx_rebase(sub, bstart));
                 let after = target.len();
                 if greedy { target[spos] = RxInst::Split(bstart, after); }
                 else { target[spos] = RxInst::Split(after, bstart); }
                 return;
             }
+            '{' => {
+                if let Some((n, m, consumed)) = rx_try_parse_count(chars, *pos) {
+                    *pos += consumed;
+                    let greedy = !(*pos < chars.len() && chars[*pos] == '?');
+                    if !greedy { *pos += 1; }
+                    if let Some(m) = m {
+                        if m < n {
+                            panic!("runtime error: invalid regex: {{{},{}}} has max less than min", n, m);
+                        }
+                    }
+                    rx_emit_counted(sub, target, n, m, greedy);
+                    return;
+                }
+            }
             _ => {}
         }
     }
-    target.extend(sub.drain(..));
+    let base = target.len();
+    target.extend(rx_rebase(sub, base));
+}
+
+/// Parses a `{n}`, `{n,}`, or `{n,m}` counted-repetition quantifier starting
+/// at `chars[start]` (which must be `{`). Returns `(n, m, chars_consumed)`
+/// on success (`m` is `None` for the unbounded `{n,}` form) or `None` if
+/// the braces don't hold a valid count, in which case `{` is left for the
+/// caller to treat as a literal character.
+fn rx_try_parse_count(chars: &[char], start: usize) -> Option<(usize, Option<usize>, usize)> {
+    let mut i = start + 1;
+    let n_start = i;
+    while i < chars.len() && chars[i].is_ascii_digit() { i += 1; }
+    if i == n_start { return None; }
+    let n: usize = chars[n_start..i].iter().collect::<String>().parse().ok()?;
+    let m = if chars.get(i) == Some(&',') {
+        i += 1;
+        let m_start = i;
+        while i < chars.len() && chars[i].is_ascii_digit() { i += 1; }
+        if i == m_start { None } else { Some(chars[m_start..i].iter().collect::<String>().parse().ok()?) }
+    } else {
+        Some(n)
+    };
+    if chars.get(i) != Some(&'}') { return None; }
+    i += 1;
+    Some((n, m, i - start))
 }
 
-fn rx_parse_class(chars: &[char], pos: &mut usize) -> (Vec<(char, char)>, bool) {
+/// Shifts every absolute `Split`/`Jump` target in `insts` by `shift`, for
+/// splicing an already-compiled, self-contained fragment (e.g. a group's
+/// body) into a target sequence at a nonzero offset.
+fn rx_rebase(insts: &[RxInst], shift: usize) -> Vec<RxInst> {
+    insts
+        .iter()
+        .map(|inst| match inst {
+            RxInst::Split(a, b) => RxInst::Split(a + shift, b + shift),
+            RxInst::Jump(t) => RxInst::Jump(t + shift),
+            other => other.clone(),
+        })
+        .collect()
+}
+
+/// Emits `n` mandatory copies of `sub` followed by the optional tail implied
+/// by `m`: `m - n` further copies each individually skippable (same `Split`
+/// shape as `?`) when `m` is bounded, or a trailing `*`-style loop over one
+/// more copy when `m` is `None` (the `{n,}` form).
+fn rx_emit_counted(sub: &[RxInst], target: &mut Vec<RxInst>, n: usize, m: Option<usize>, greedy: bool) {
+    for _ in 0..n {
+        let base = target.len();
+        target.extend(rx_rebase(sub, base));
+    }
+    match m {
+        Some(m) => {
+            for _ in 0..(m - n) {
+                let spos = target.len();
+                target.push(RxInst::Split(0, 0)); // placeholder
+                let bstart = target.len();
+                target.extend(rx_rebase(sub, bstart));
+                let after = target.len();
+                target[spos] = if greedy { RxInst::Split(bstart, after) } else { RxInst::Split(after, bstart) };
+            }
+        }
+        None => {
+            let spos = target.len();
+            target.push(RxInst::Split(0, 0)); // placeholder
+            let bstart = target.len();
+            target.extend(rx_rebase(sub, bstart));
+            target.push(RxInst::Jump(spos));
+            let after = target.len();
+            target[spos] = if greedy { RxInst::Split(bstart, after) } else { RxInst::Split(after, bstart) };
+        }
+    }
+}
+
+fn rx_parse_class(chars: &[char], pos: &mut usize) -> (Vec<(char, char)>, Vec<UnicodeCategory>, bool) {
     let mut ranges = Vec::new();
+    let mut cats = Vec::new();
     let neg = *pos < chars.len() && chars[*pos] == '^';
     if neg { *pos += 1; }
     while *pos < chars.len() && chars[*pos] != ']' {
@@ -1641,6 +3284,10 @@ fn rx_parse_class(chars: &[char], pos: &mut usize) -> (Vec<(char, char)>, bool)
                 'd' => { ranges.push(('0', '9')); continue; }
                 'w' => { ranges.extend_from_slice(&[('a','z'),('A','Z'),('0','9'),('_','_')]); continue; }
                 's' => { ranges.extend_from_slice(&[(' ',' '),('\t','\t'),('\n','\n'),('\r','\r')]); continue; }
+                'p' => match rx_parse_category_name(chars, pos) {
+                    Some(cat) => { cats.push(cat); continue; }
+                    None => esc,
+                },
                 _ => esc,
             }
         } else { ch };
@@ -1653,46 +3300,61 @@ fn rx_parse_class(chars: &[char], pos: &mut usize) -> (Vec<(char, char)>, bool)
         }
     }
     if *pos < chars.len() && chars[*pos] == ']' { *pos += 1; }
-    (ranges, neg)
+    (ranges, cats, neg)
 }
 
-fn rx_match_at(insts: &[RxInst], input: &[char], start: usize, ci: bool) -> Option<usize> {
-    let mut cur: Vec<usize> = Vec::new();
-    let mut nxt: Vec<usize> = Vec::new();
-    let mut best: Option<usize> = None;
+type RxThread = (usize, Arc<Vec<Option<usize>>>);
+
+/// Runs the Pike VM from `start`, returning the end position of the
+/// longest match among all threads active at `start` (not leftmost-first
+/// priority) plus its capture slots (`None` for groups that didn't
+/// participate), or `None` if no match starts at `start`.
+fn rx_match_at(insts: &[RxInst], input: &[char], start: usize, ci: bool, nslots: usize) -> Option<(usize, Arc<Vec<Option<usize>>>)> {
+    let mut cur: Vec<RxThread> = Vec::new();
+    let mut nxt: Vec<RxThread> = Vec::new();
+    let mut best: Option<(usize, Arc<Vec<Option<usize>>>)> = None;
 
-    rx_add_thread(&mut cur, insts, 0, input, start);
+    let init_slots = Arc::new(vec![None; nslots]);
+    rx_add_thread(&mut cur, insts, 0, input, start, init_slots);
 
     let mut p = start;
     loop {
         if cur.is_empty() { break; }
         // Collect deferred additions for AnchorEnd (cannot mutate cur while iterating)
-        let mut anchor_end_adds: Vec<usize> = Vec::new();
-        for &pc in &cur {
+        let mut anchor_end_adds: Vec<RxThread> = Vec::new();
+        for (pc, slots) in &cur {
+            let pc = *pc;
             if pc >= insts.len() { continue; }
             match &insts[pc] {
-                RxInst::Match => { if best.is_none() || p > best.unwrap() { best = Some(p); } }
-                RxInst::Lit(ch) => { if p < input.len() && input[p] == *ch { rx_add_thread(&mut nxt, insts, pc + 1, input, p + 1); } }
-                RxInst::LitCI(lo, hi) => { if p < input.len() && (input[p] == *lo || input[p] == *hi) { rx_add_thread(&mut nxt, insts, pc + 1, input, p + 1); } }
-                RxInst::Dot => { if p < input.len() && input[p] != '\n' { rx_add_thread(&mut nxt, insts, pc + 1, input, p + 1); } }
-                RxInst::Class(ranges, neg) => {
+                RxInst::Match => { if best.is_none() || p > best.as_ref().unwrap().0 { best = Some((p, slots.clone())); } }
+                RxInst::Lit(ch) => { if p < input.len() && input[p] == *ch { rx_add_thread(&mut nxt, insts, pc + 1, input, p + 1, slots.clone()); } }
+                RxInst::LitCI(variants) => {
+                    if p < input.len() && rx_case_variants(input[p]).iter().any(|v| variants.contains(v)) {
+                        rx_add_thread(&mut nxt, insts, pc + 1, input, p + 1, slots.clone());
+                    }
+                }
+                RxInst::Dot => { if p < input.len() && input[p] != '\n' { rx_add_thread(&mut nxt, insts, pc + 1, input, p + 1, slots.clone()); } }
+                RxInst::Class(ranges, cats, neg) => {
                     if p < input.len() {
-                        let c = if ci { input[p].to_lowercase().next().unwrap_or(input[p]) } else { input[p] };
-                        let mut in_class = false;
-                        for &(lo, hi) in ranges {
-                            let (lo, hi) = if ci { (lo.to_lowercase().next().unwrap_or(lo), hi.to_lowercase().next().unwrap_or(hi)) } else { (lo, hi) };
-                            if c >= lo && c <= hi { in_class = true; break; }
-                        }
-                        if in_class != *neg { rx_add_thread(&mut nxt, insts, pc + 1, input, p + 1); }
+                        let c = input[p];
+                        let fold = if ci { rx_case_variants(c) } else { vec![c] };
+                        let mut in_class = fold.iter().any(|fc| ranges.iter().any(|&(lo, hi)| *fc >= lo && *fc <= hi));
+                        if !in_class { in_class = cats.iter().any(|cat| rx_category_matches(*cat, c)); }
+                        if in_class != *neg { rx_add_thread(&mut nxt, insts, pc + 1, input, p + 1, slots.clone()); }
                     }
                 }
-                RxInst::AnchorEnd => { if p == input.len() { anchor_end_adds.push(pc + 1); } }
+                RxInst::UnicodeClass(cat, neg) => {
+                    if p < input.len() {
+                        if rx_category_matches(*cat, input[p]) != *neg { rx_add_thread(&mut nxt, insts, pc + 1, input, p + 1, slots.clone()); }
+                    }
+                }
+                RxInst::AnchorEnd => { if p == input.len() { anchor_end_adds.push((pc + 1, slots.clone())); } }
                 _ => {}
             }
         }
         // Apply deferred AnchorEnd thread additions
-        for add_pc in anchor_end_adds {
-            rx_add_thread(&mut cur, insts, add_pc, input, p);
+        for (add_pc, slots) in anchor_end_adds {
+            rx_add_thread(&mut cur, insts, add_pc, input, p, slots);
         }
         cur.clear();
         std::mem::swap(&mut cur, &mut nxt);
@@ -1702,30 +3364,47 @@ fn rx_match_at(insts: &[RxInst], input: &[char], start: usize, ci: bool) -> Opti
     best
 }
 
-fn rx_add_thread(threads: &mut Vec<usize>, insts: &[RxInst], pc: usize, input: &[char], pos: usize) {
-    if pc >= insts.len() || threads.contains(&pc) { return; }
+fn rx_add_thread(threads: &mut Vec<RxThread>, insts: &[RxInst], pc: usize, input: &[char], pos: usize, slots: Arc<Vec<Option<usize>>>) {
+    if pc >= insts.len() || threads.iter().any(|(p, _)| *p == pc) { return; }
     match &insts[pc] {
-        RxInst::Split(a, b) => { rx_add_thread(threads, insts, *a, input, pos); rx_add_thread(threads, insts, *b, input, pos); }
-        RxInst::Jump(t) => { rx_add_thread(threads, insts, *t, input, pos); }
-        RxInst::AnchorStart => { if pos == 0 { rx_add_thread(threads, insts, pc + 1, input, pos); } }
-        _ => { threads.push(pc); }
+        RxInst::Split(a, b) => {
+            rx_add_thread(threads, insts, *a, input, pos, slots.clone());
+            rx_add_thread(threads, insts, *b, input, pos, slots);
+        }
+        RxInst::Jump(t) => { rx_add_thread(threads, insts, *t, input, pos, slots); }
+        RxInst::AnchorStart => { if pos == 0 { rx_add_thread(threads, insts, pc + 1, input, pos, slots); } }
+        RxInst::Save(n) => {
+            let mut updated = (*slots).clone();
+            updated[*n] = Some(pos);
+            rx_add_thread(threads, insts, pc + 1, input, pos, Arc::new(updated));
+        }
+        RxInst::WordBoundary(want) => {
+            let before = pos > 0 && is_word_char(input[pos - 1]);
+            let after = pos < input.len() && is_word_char(input[pos]);
+            if (before != after) == *want { rx_add_thread(threads, insts, pc + 1, input, pos, slots); }
+        }
+        _ => { threads.push((pc, slots)); }
     }
 }
 
-fn rx_search(insts: &[RxInst], input: &str, ci: bool) -> bool {
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn rx_search(insts: &[RxInst], nslots: usize, input: &str, ci: bool) -> bool {
     let chars: Vec<char> = input.chars().collect();
     for s in 0..=chars.len() {
-        if rx_match_at(insts, &chars, s, ci).is_some() { return true; }
+        if rx_match_at(insts, &chars, s, ci, nslots).is_some() { return true; }
     }
     false
 }
 
-fn rx_find_all(insts: &[RxInst], input: &str, ci: bool) -> Vec<String> {
+fn rx_find_all(insts: &[RxInst], nslots: usize, input: &str, ci: bool) -> Vec<String> {
     let chars: Vec<char> = input.chars().collect();
     let mut results = Vec::new();
     let mut p = 0;
     while p <= chars.len() {
-        if let Some(end) = rx_match_at(insts, &chars, p, ci) {
+        if let Some((end, _)) = rx_match_at(insts, &chars, p, ci, nslots) {
             if end > p { results.push(chars[p..end].iter().collect()); p = end; continue; }
         }
         p += 1;
@@ -1733,13 +3412,48 @@ fn rx_find_all(insts: &[RxInst], input: &str, ci: bool) -> Vec<String> {
     results
 }
 
-fn rx_replace_all(insts: &[RxInst], input: &str, repl: &str, ci: bool) -> String {
+/// Expands `$0`/`$1`.../`$$` backreferences in `repl` against one match's
+/// captured slots (`$0` is the whole match; `$$` is a literal `$`).
+fn rx_expand_replacement(repl: &str, input: &[char], slots: &[Option<usize>]) -> String {
+    let rchars: Vec<char> = repl.chars().collect();
+    let mut out = String::new();
+    let mut i = 0;
+    while i < rchars.len() {
+        if rchars[i] == '$' && i + 1 < rchars.len() {
+            if rchars[i + 1] == '$' {
+                out.push('$');
+                i += 2;
+                continue;
+            }
+            if rchars[i + 1].is_ascii_digit() {
+                let start = i + 1;
+                let mut j = start;
+                while j < rchars.len() && rchars[j].is_ascii_digit() { j += 1; }
+                let group: usize = rchars[start..j].iter().collect::<String>().parse().unwrap_or(0);
+                if let (Some(Some(s)), Some(Some(e))) = (slots.get(group * 2), slots.get(group * 2 + 1)) {
+                    out.push_str(&input[*s..*e].iter().collect::<String>());
+                }
+                i = j;
+                continue;
+            }
+        }
+        out.push(rchars[i]);
+        i += 1;
+    }
+    out
+}
+
+fn rx_replace_all(insts: &[RxInst], nslots: usize, input: &str, repl: &str, ci: bool) -> String {
     let chars: Vec<char> = input.chars().collect();
     let mut result = String::new();
     let mut p = 0;
     while p < chars.len() {
-        if let Some(end) = rx_match_at(insts, &chars, p, ci) {
-            if end > p { result.push_str(repl); p = end; continue; }
+        if let Some((end, slots)) = rx_match_at(insts, &chars, p, ci, nslots) {
+            if end > p {
+                result.push_str(&rx_expand_replacement(repl, &chars, &slots));
+                p = end;
+                continue;
+            }
         }
         result.push(chars[p]);
         p += 1;
@@ -1747,13 +3461,13 @@ fn rx_replace_all(insts: &[RxInst], input: &str, repl: &str, ci: bool) -> String
     result
 }
 
-fn rx_split(insts: &[RxInst], input: &str, ci: bool) -> Vec<String> {
+fn rx_split(insts: &[RxInst], nslots: usize, input: &str, ci: bool) -> Vec<String> {
     let chars: Vec<char> = input.chars().collect();
     let mut parts = Vec::new();
     let mut last = 0;
     let mut p = 0;
     while p < chars.len() {
-        if let Some(end) = rx_match_at(insts, &chars, p, ci) {
+        if let Some((end, _)) = rx_match_at(insts, &chars, p, ci, nslots) {
             if end > p { parts.push(chars[last..p].iter().collect()); last = end; p = end; continue; }
         }
         p += 1;
@@ -1770,16 +3484,16 @@ fn regex_match_val(string: &Value, pattern: &Value, flags: &Value) -> Value {
     let s = match string { Value::Str(st) => st.clone(), _ => panic!("regex: string must be a string") };
     let p = match pattern { Value::Str(st) => st.clone(), _ => panic!("regex: pattern must be a string") };
     let ci = rx_get_flags(flags);
-    let insts = rx_compile(&p, ci);
-    Value::Bool(rx_search(&insts, &s, ci))
+    let (insts, groups) = rx_compile(&p, ci);
+    Value::Bool(rx_search(&insts, (groups + 1) * 2, &s, ci))
 }
 
 fn regex_find_all_val(string: &Value, pattern: &Value, flags: &Value) -> Value {
     let s = match string { Value::Str(st) => st.clone(), _ => panic!("regex: string must be a string") };
     let p = match pattern { Value::Str(st) => st.clone(), _ => panic!("regex: pattern must be a string") };
     let ci = rx_get_flags(flags);
-    let insts = rx_compile(&p, ci);
-    let matches = rx_find_all(&insts, &s, ci);
+    let (insts, groups) = rx_compile(&p, ci);
+    let matches = rx_find_all(&insts, (groups + 1) * 2, &s, ci);
     make_array(matches.into_iter().map(|m| Value::Str(m)).collect())
 }
 
@@ -1788,16 +3502,41 @@ fn regex_replace_val(string: &Value, pattern: &Value, replacement: &Value, flags
     let p = match pattern { Value::Str(st) => st.clone(), _ => panic!("regex: pattern must be a string") };
     let r = match replacement { Value::Str(st) => st.clone(), _ => panic!("regex: replacement must be a string") };
     let ci = rx_get_flags(flags);
-    let insts = rx_compile(&p, ci);
-    Value::Str(rx_replace_all(&insts, &s, &r, ci))
+    let (insts, groups) = rx_compile(&p, ci);
+    Value::Str(rx_replace_all(&insts, (groups + 1) * 2, &s, &r, ci))
 }
 
 fn regex_split_val(string: &Value, pattern: &Value, flags: &Value) -> Value {
     let s = match string { Value::Str(st) => st.clone(), _ => panic!("regex: string must be a string") };
     let p = match pattern { Value::Str(st) => st.clone(), _ => panic!("regex: pattern must be a string") };
     let ci = rx_get_flags(flags);
-    let insts = rx_compile(&p, ci);
-    make_array(rx_split(&insts, &s, ci).into_iter().map(|p| Value::Str(p)).collect())
+    let (insts, groups) = rx_compile(&p, ci);
+    make_array(rx_split(&insts, (groups + 1) * 2, &s, ci).into_iter().map(|p| Value::Str(p)).collect())
+}
+
+/// `regex_captures(string, pattern, flags)` builtin: returns the first
+/// match as an array of strings (the whole match at index 0, then each
+/// subgroup in order), with `None` for groups that didn't participate —
+/// or `Value::None` if the pattern doesn't match at all.
+fn regex_captures(string: &Value, pattern: &Value, flags: &Value) -> Value {
+    let s = match string { Value::Str(st) => st.clone(), _ => panic!("regex: string must be a string") };
+    let p = match pattern { Value::Str(st) => st.clone(), _ => panic!("regex: pattern must be a string") };
+    let ci = rx_get_flags(flags);
+    let (insts, groups) = rx_compile(&p, ci);
+    let nslots = (groups + 1) * 2;
+    let chars: Vec<char> = s.chars().collect();
+    for start in 0..=chars.len() {
+        if let Some((_, slots)) = rx_match_at(&insts, &chars, start, ci, nslots) {
+            let captures: Vec<Value> = (0..=groups)
+                .map(|g| match (slots[g * 2], slots[g * 2 + 1]) {
+                    (Some(s), Some(e)) => Value::Str(chars[s..e].iter().collect()),
+                    _ => Value::None,
+                })
+                .collect();
+            return make_array(captures);
+        }
+    }
+    Value::None
 }
 
 // ============================================================================